@@ -0,0 +1,170 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::tdms_parse_error::TdmsParseError;
+
+#[derive(Debug)]
+pub struct BatchResult {
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub error: Option<TdmsParseError>,
+}
+
+/// Expands the command line inputs into the list of TDMS files to index.
+///
+/// Directories are searched recursively for `*.tdms` files, inputs containing
+/// `*`, `?` or `[` are treated as glob patterns and everything else is taken as
+/// a file path as is.
+pub fn collect_inputs(inputs: &[&str]) -> Result<Vec<PathBuf>, TdmsParseError> {
+    let mut files = vec![];
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            collect_directory(path, &mut files)?;
+        } else if input.contains(&['*', '?', '['][..]) {
+            let paths = glob::glob(input).map_err(|e| TdmsParseError::GeneralError {
+                message: format!("invalid pattern {}: {}", input, e),
+            })?;
+            let count = files.len();
+            for entry in paths {
+                match entry {
+                    Ok(path) if path.is_file() => files.push(path),
+                    Ok(_) => {}
+                    Err(e) => warn!("{}", e),
+                }
+            }
+            if files.len() == count {
+                warn!("pattern {} does not match any file", input);
+            }
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|path| seen.insert(path.clone()));
+    Ok(files)
+}
+
+/// Recursively collects the `*.tdms` files below `dir` in sorted order.
+pub fn collect_directory(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), TdmsParseError> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_directory(&path, files)?;
+        } else if is_tdms_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_tdms_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tdms"))
+}
+
+/// Runs `index` for every file on a pool of `jobs` worker threads.
+///
+/// The results are returned in the order of `files`. A panic while indexing a
+/// file is recorded as the error of that file.
+pub fn run<F>(files: &[PathBuf], jobs: usize, index: F) -> Vec<BatchResult>
+where
+    F: Fn(&Path) -> Result<(), TdmsParseError> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1).min(files.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= files.len() {
+                    break;
+                }
+
+                let path = &files[i];
+                info!("indexing {}", path.display());
+                let start = Instant::now();
+                let error = match panic::catch_unwind(AssertUnwindSafe(|| index(path))) {
+                    Ok(result) => result.err(),
+                    Err(payload) => Some(TdmsParseError::GeneralError {
+                        message: format!("panicked: {}", panic_message(&*payload)),
+                    }),
+                };
+                if let Some(e) = &error {
+                    error!("{}: {:?}", path.display(), e);
+                }
+                let result = BatchResult {
+                    path: path.clone(),
+                    duration: start.elapsed(),
+                    error,
+                };
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown cause"
+    }
+}
+
+pub fn print_summary(results: &[BatchResult]) {
+    // a closed stdout leaves nothing to report to
+    let _ = write_summary(&mut io::stdout().lock(), results);
+}
+
+/// Writes one line per file and the totals.
+pub fn write_summary<W: Write>(out: &mut W, results: &[BatchResult]) -> io::Result<()> {
+    for result in results {
+        match &result.error {
+            None => writeln!(
+                out,
+                "OK    {:>9.3}s  {}",
+                result.duration.as_secs_f64(),
+                result.path.display()
+            )?,
+            Some(e) => writeln!(
+                out,
+                "FAIL  {:>9.3}s  {}: {}",
+                result.duration.as_secs_f64(),
+                result.path.display(),
+                e
+            )?,
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let total: Duration = results.iter().map(|r| r.duration).sum();
+    writeln!(
+        out,
+        "{} succeeded, {} failed, {:.3}s total indexing time",
+        results.len() - failed,
+        failed,
+        total.as_secs_f64()
+    )
+}
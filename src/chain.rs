@@ -0,0 +1,421 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::to_writer_pretty;
+
+use crate::hvds::index_with;
+use crate::output::Output;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::util::StringDecoding;
+
+pub const RECORDING_SCHEMA_ID: &str = "http://audi.de/adas/logging/hvds/recording/V0_0_1";
+
+/// A split of a recording with its parsed index.
+#[derive(Debug)]
+pub struct Split {
+    pub path: PathBuf,
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChainIssueKind {
+    /// The split named in `SplitBefore`/`SplitAfter` does not exist.
+    MissingSplit,
+    /// The split exists but cannot be parsed.
+    UnreadableSplit,
+    /// A split is linked a second time.
+    Cycle,
+    /// The neighbour does not link back to the split.
+    BrokenLink,
+    /// The stop time of a split differs from the start time of the next one.
+    TimeGap,
+    /// The first frame number of a split does not continue the previous split.
+    FrameNumberGap,
+    /// The splits have different `ReferenceFileName`s.
+    DifferentRecording,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainIssue {
+    /// The split the issue was found at, for gaps the later of the two.
+    #[serde(rename = "RawFile")]
+    pub raw_file: String,
+
+    #[serde(rename = "Kind")]
+    pub kind: ChainIssueKind,
+
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitEntry {
+    #[serde(rename = "RawFile")]
+    pub raw_file: String,
+
+    #[serde(rename = "SplitStartTime")]
+    pub split_start_time: u64,
+
+    #[serde(rename = "SplitStopTime")]
+    pub split_stop_time: u64,
+
+    #[serde(rename = "FirstTimestamp")]
+    pub first_timestamp: Option<u64>,
+
+    #[serde(rename = "LastTimestamp")]
+    pub last_timestamp: Option<u64>,
+
+    #[serde(rename = "FirstFrameNumber")]
+    pub first_frame_number: Option<u32>,
+
+    #[serde(rename = "LastFrameNumber")]
+    pub last_frame_number: Option<u32>,
+
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recording {
+    #[serde(rename = "ReferenceFileName")]
+    pub reference_file_name: String,
+
+    #[serde(rename = "StartTime")]
+    pub start_time: u64,
+
+    #[serde(rename = "StopTime")]
+    pub stop_time: u64,
+
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+
+    /// The splits in recording order.
+    #[serde(rename = "Splits")]
+    pub splits: Vec<SplitEntry>,
+
+    #[serde(rename = "Issues")]
+    pub issues: Vec<ChainIssue>,
+}
+
+/// The recording-level index written by the `chain` command.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordingIndex {
+    pub schema: String,
+
+    #[serde(rename = "Recording")]
+    pub recording: Recording,
+}
+
+/// Follows the `SplitBefore`/`SplitAfter` links from `path` in both directions
+/// and returns the splits in recording order with the continuity issues found.
+///
+/// Only an error for `path` itself fails the chain, problems with the linked
+/// splits end the chain in that direction and are reported as issues.
+pub fn follow_chain(path: &Path) -> Result<(Vec<Split>, Vec<ChainIssue>), TdmsParseError> {
+    follow_chain_with(path, StringDecoding::Strict)
+}
+
+/// Like [`follow_chain`], decoding the strings of the splits with `strings`.
+pub fn follow_chain_with(
+    path: &Path,
+    strings: StringDecoding,
+) -> Result<(Vec<Split>, Vec<ChainIssue>), TdmsParseError> {
+    let mut issues = vec![];
+    let mut visited = HashSet::new();
+    visited.insert(fs::canonicalize(path)?);
+    let start = load_split(path, strings)?;
+
+    let mut before = vec![];
+    follow_links(
+        &start,
+        true,
+        strings,
+        &mut before,
+        &mut visited,
+        &mut issues,
+    );
+    let mut after = vec![];
+    follow_links(
+        &start,
+        false,
+        strings,
+        &mut after,
+        &mut visited,
+        &mut issues,
+    );
+
+    let mut splits = before;
+    splits.reverse();
+    splits.push(start);
+    splits.append(&mut after);
+
+    for pair in splits.windows(2) {
+        check_continuity(&pair[0], &pair[1], &mut issues);
+    }
+    Ok((splits, issues))
+}
+
+/// Follows the chain from `path` and summarizes it.
+pub fn chain(path: &Path) -> Result<Recording, TdmsParseError> {
+    let (splits, issues) = follow_chain(path)?;
+    recording(&splits, issues).ok_or_else(|| empty_chain(path))
+}
+
+/// The error for a chain without splits, `follow_chain` always returns the
+/// split it started from.
+pub fn empty_chain(path: &Path) -> TdmsParseError {
+    TdmsParseError::GeneralError {
+        message: format!("no splits found in the chain of {}", path.display()),
+    }
+}
+
+/// Summarizes the splits of a recording, `None` without any split.
+pub fn recording(splits: &[Split], issues: Vec<ChainIssue>) -> Option<Recording> {
+    let first = &splits.first()?.output.file.metadata;
+    let last = &splits.last()?.output.file.metadata;
+    let entries: Vec<SplitEntry> = splits.iter().map(split_entry).collect();
+    Some(Recording {
+        reference_file_name: first.reference_file_name.clone(),
+        start_time: first.reference_split_start_time,
+        stop_time: last.reference_split_stop_time,
+        frame_count: entries.iter().map(|entry| entry.frame_count).sum(),
+        splits: entries,
+        issues,
+    })
+}
+
+/// Default location of the recording index: next to the first split.
+pub fn recording_index_path(splits: &[Split]) -> Option<PathBuf> {
+    Some(splits.first()?.path.with_extension("recording.json"))
+}
+
+pub fn write_recording(path: &Path, recording: Recording) -> Result<(), TdmsParseError> {
+    let output_error = |message: String| TdmsParseError::OutputError {
+        path: path.to_string_lossy().to_string(),
+        message,
+    };
+    let index = RecordingIndex {
+        schema: RECORDING_SCHEMA_ID.to_string(),
+        recording,
+    };
+    let file = File::create(path).map_err(|e| output_error(e.to_string()))?;
+    to_writer_pretty(&file, &index).map_err(|e| output_error(e.to_string()))
+}
+
+pub fn print_recording(recording: &Recording) {
+    println!(
+        "{:>4}  {:<40}  {:>20}  {:>20}  {:>10}  {:>10}  {:>8}",
+        "#", "RawFile", "SplitStartTime", "SplitStopTime", "FirstFrame", "LastFrame", "Frames"
+    );
+    let none = || "-".to_string();
+    for (i, split) in recording.splits.iter().enumerate() {
+        println!(
+            "{:>4}  {:<40}  {:>20}  {:>20}  {:>10}  {:>10}  {:>8}",
+            i,
+            split.raw_file,
+            split.split_start_time,
+            split.split_stop_time,
+            split
+                .first_frame_number
+                .map_or_else(none, |n| n.to_string()),
+            split.last_frame_number.map_or_else(none, |n| n.to_string()),
+            split.frame_count
+        );
+    }
+    for issue in recording.issues.iter() {
+        println!("{:?} at {}: {}", issue.kind, issue.raw_file, issue.message);
+    }
+    println!(
+        "{} splits, {} frames, {} issues",
+        recording.splits.len(),
+        recording.frame_count,
+        recording.issues.len()
+    );
+}
+
+fn load_split(path: &Path, strings: StringDecoding) -> Result<Split, TdmsParseError> {
+    debug!("indexing split {}", path.display());
+    let output = index_with(&path.to_string_lossy(), strings)?;
+    Ok(Split {
+        path: path.to_path_buf(),
+        output,
+    })
+}
+
+fn follow_links(
+    start: &Split,
+    backwards: bool,
+    strings: StringDecoding,
+    splits: &mut Vec<Split>,
+    visited: &mut HashSet<PathBuf>,
+    issues: &mut Vec<ChainIssue>,
+) {
+    loop {
+        let current = splits.last().unwrap_or(start);
+        let name = link(current, backwards);
+        if name.is_empty() {
+            return;
+        }
+
+        let issue = |kind, message| ChainIssue {
+            raw_file: raw_file(current),
+            kind,
+            message,
+        };
+        let path = match resolve(&current.path, name) {
+            Some(path) => path,
+            None => {
+                issues.push(issue(
+                    ChainIssueKind::MissingSplit,
+                    format!("linked split {} not found", name),
+                ));
+                return;
+            }
+        };
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if !visited.insert(canonical) {
+            issues.push(issue(
+                ChainIssueKind::Cycle,
+                format!("{} is already part of the chain", name),
+            ));
+            return;
+        }
+        match load_split(&path, strings) {
+            Ok(split) => splits.push(split),
+            Err(e) => {
+                issues.push(issue(
+                    ChainIssueKind::UnreadableSplit,
+                    format!("{}: {}", path.display(), e),
+                ));
+                return;
+            }
+        }
+    }
+}
+
+fn check_continuity(previous: &Split, next: &Split, issues: &mut Vec<ChainIssue>) {
+    let mut issue = |kind, message| {
+        issues.push(ChainIssue {
+            raw_file: raw_file(next),
+            kind,
+            message,
+        })
+    };
+
+    if !links_to(previous, false, next) || !links_to(next, true, previous) {
+        issue(
+            ChainIssueKind::BrokenLink,
+            format!(
+                "{} and {} do not link to each other",
+                raw_file(previous),
+                raw_file(next)
+            ),
+        );
+    }
+
+    let previous_metadata = &previous.output.file.metadata;
+    let next_metadata = &next.output.file.metadata;
+    if previous_metadata.reference_file_name != next_metadata.reference_file_name {
+        issue(
+            ChainIssueKind::DifferentRecording,
+            format!(
+                "ReferenceFileName {} follows {}",
+                next_metadata.reference_file_name, previous_metadata.reference_file_name
+            ),
+        );
+    }
+    if previous_metadata.reference_split_stop_time != next_metadata.reference_split_start_time {
+        issue(
+            ChainIssueKind::TimeGap,
+            format!(
+                "SplitStartTime {} does not match SplitStopTime {} of {}",
+                next_metadata.reference_split_start_time,
+                previous_metadata.reference_split_stop_time,
+                raw_file(previous)
+            ),
+        );
+    }
+
+    let last = previous.output.file.stream.data.frame_number.last();
+    let first = next.output.file.stream.data.frame_number.first();
+    if let (Some(&last), Some(&first)) = (last, first) {
+        if first != last.wrapping_add(1) {
+            issue(
+                ChainIssueKind::FrameNumberGap,
+                format!(
+                    "first FrameNumber {} does not follow {} of {}",
+                    first,
+                    last,
+                    raw_file(previous)
+                ),
+            );
+        }
+    }
+}
+
+fn link(split: &Split, backwards: bool) -> &str {
+    let metadata = &split.output.file.metadata;
+    if backwards {
+        &metadata.reference_split_before
+    } else {
+        &metadata.reference_split_after
+    }
+}
+
+fn links_to(split: &Split, backwards: bool, target: &Split) -> bool {
+    match resolve(&split.path, link(split, backwards)) {
+        Some(path) => same_file(&path, &target.path),
+        None => false,
+    }
+}
+
+/// Resolves a split name relative to the directory of `from`, the `.tdms`
+/// extension may be omitted.
+fn resolve(from: &Path, name: &str) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    let dir = from.parent().unwrap_or_else(|| Path::new(""));
+    let path = dir.join(name);
+    if path.is_file() {
+        return Some(path);
+    }
+    let path = dir.join(format!("{}.tdms", name));
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn raw_file(split: &Split) -> String {
+    split
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn split_entry(split: &Split) -> SplitEntry {
+    let metadata = &split.output.file.metadata;
+    let data = &split.output.file.stream.data;
+    SplitEntry {
+        raw_file: raw_file(split),
+        split_start_time: metadata.reference_split_start_time,
+        split_stop_time: metadata.reference_split_stop_time,
+        first_timestamp: data.timestamp.first().copied(),
+        last_timestamp: data.timestamp.last().copied(),
+        first_frame_number: data.frame_number.first().copied(),
+        last_frame_number: data.frame_number.last().copied(),
+        frame_count: data.frame_number.len(),
+    }
+}
@@ -0,0 +1,574 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use chrono::{LocalResult, SecondsFormat, TimeZone, Utc};
+
+use crate::data_channel::DataSegment;
+use crate::tdms_datatype::TdmsDataType;
+use crate::tdms_lead_in::{has_lead_in_at, LeadIn};
+use crate::tdms_metadata::Metadata;
+use crate::tdms_object::RawDataIndex;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::{SegmentReader, INCOMPLETE_SEGMENT_OFFSET};
+use crate::util::{decode_string, StringDecoding};
+
+/// Seconds between the TDMS epoch (1904-01-01 UTC) and the Unix epoch.
+const TDMS_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// A TDMS timestamp: seconds since 1904-01-01 UTC and positive fractions of 2^-64 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TdmsTimestamp {
+    pub seconds: i64,
+    pub fractions: u64,
+}
+
+impl TdmsTimestamp {
+    /// Nanoseconds since the Unix epoch.
+    pub fn unix_nanos(&self) -> i128 {
+        (self.seconds as i128 - TDMS_EPOCH_OFFSET as i128) * 1_000_000_000
+            + self.subsec_nanos() as i128
+    }
+
+    fn subsec_nanos(&self) -> u32 {
+        ((self.fractions as u128 * 1_000_000_000) >> 64) as u32
+    }
+
+    /// ISO 8601 representation in UTC, the raw seconds and fractions if the
+    /// time is out of the range of `chrono`.
+    pub fn to_iso8601(&self) -> String {
+        let unix_seconds = self.seconds.checked_sub(TDMS_EPOCH_OFFSET);
+        match unix_seconds.map(|seconds| Utc.timestamp_opt(seconds, self.subsec_nanos())) {
+            Some(LocalResult::Single(time)) => time.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            _ => format!("{}.{:020}", self.seconds, self.fractions),
+        }
+    }
+}
+
+/// The decoded values of a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelData {
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    ComplexF32(Vec<(f32, f32)>),
+    ComplexF64(Vec<(f64, f64)>),
+    Boolean(Vec<bool>),
+    TimeStamp(Vec<TdmsTimestamp>),
+    String(Vec<String>),
+}
+
+impl ChannelData {
+    /// Empty values of `datatype`, `None` if values of the type cannot be decoded.
+    pub fn new(datatype: &TdmsDataType) -> Option<Self> {
+        let data = match datatype {
+            TdmsDataType::I8 => ChannelData::I8(vec![]),
+            TdmsDataType::I16 => ChannelData::I16(vec![]),
+            TdmsDataType::I32 => ChannelData::I32(vec![]),
+            TdmsDataType::I64 => ChannelData::I64(vec![]),
+            TdmsDataType::U8 => ChannelData::U8(vec![]),
+            TdmsDataType::U16 => ChannelData::U16(vec![]),
+            TdmsDataType::U32 => ChannelData::U32(vec![]),
+            TdmsDataType::U64 => ChannelData::U64(vec![]),
+            TdmsDataType::SingleFloat | TdmsDataType::SingleFloatWithUnit => {
+                ChannelData::F32(vec![])
+            }
+            TdmsDataType::DoubleFloat | TdmsDataType::DoubleFloatWithUnit => {
+                ChannelData::F64(vec![])
+            }
+            TdmsDataType::ComplexSingleFloat => ChannelData::ComplexF32(vec![]),
+            TdmsDataType::ComplexDoubleFloat => ChannelData::ComplexF64(vec![]),
+            TdmsDataType::Boolean => ChannelData::Boolean(vec![]),
+            TdmsDataType::TimeStamp => ChannelData::TimeStamp(vec![]),
+            TdmsDataType::String => ChannelData::String(vec![]),
+            _ => return None,
+        };
+        Some(data)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ChannelData::I8(values) => values.len(),
+            ChannelData::I16(values) => values.len(),
+            ChannelData::I32(values) => values.len(),
+            ChannelData::I64(values) => values.len(),
+            ChannelData::U8(values) => values.len(),
+            ChannelData::U16(values) => values.len(),
+            ChannelData::U32(values) => values.len(),
+            ChannelData::U64(values) => values.len(),
+            ChannelData::F32(values) => values.len(),
+            ChannelData::F64(values) => values.len(),
+            ChannelData::ComplexF32(values) => values.len(),
+            ChannelData::ComplexF64(values) => values.len(),
+            ChannelData::Boolean(values) => values.len(),
+            ChannelData::TimeStamp(values) => values.len(),
+            ChannelData::String(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value in `row` as text, timestamps in ISO 8601.
+    pub fn value_string(&self, row: usize) -> String {
+        match self {
+            ChannelData::I8(values) => values[row].to_string(),
+            ChannelData::I16(values) => values[row].to_string(),
+            ChannelData::I32(values) => values[row].to_string(),
+            ChannelData::I64(values) => values[row].to_string(),
+            ChannelData::U8(values) => values[row].to_string(),
+            ChannelData::U16(values) => values[row].to_string(),
+            ChannelData::U32(values) => values[row].to_string(),
+            ChannelData::U64(values) => values[row].to_string(),
+            ChannelData::F32(values) => values[row].to_string(),
+            ChannelData::F64(values) => values[row].to_string(),
+            ChannelData::ComplexF32(values) => format!("{}{:+}i", values[row].0, values[row].1),
+            ChannelData::ComplexF64(values) => format!("{}{:+}i", values[row].0, values[row].1),
+            ChannelData::Boolean(values) => values[row].to_string(),
+            ChannelData::TimeStamp(values) => values[row].to_iso8601(),
+            ChannelData::String(values) => values[row].clone(),
+        }
+    }
+
+    /// Appends the values of `other`, which must hold the same type.
+    pub fn append(&mut self, other: ChannelData) -> bool {
+        match (self, other) {
+            (ChannelData::I8(a), ChannelData::I8(mut b)) => a.append(&mut b),
+            (ChannelData::I16(a), ChannelData::I16(mut b)) => a.append(&mut b),
+            (ChannelData::I32(a), ChannelData::I32(mut b)) => a.append(&mut b),
+            (ChannelData::I64(a), ChannelData::I64(mut b)) => a.append(&mut b),
+            (ChannelData::U8(a), ChannelData::U8(mut b)) => a.append(&mut b),
+            (ChannelData::U16(a), ChannelData::U16(mut b)) => a.append(&mut b),
+            (ChannelData::U32(a), ChannelData::U32(mut b)) => a.append(&mut b),
+            (ChannelData::U64(a), ChannelData::U64(mut b)) => a.append(&mut b),
+            (ChannelData::F32(a), ChannelData::F32(mut b)) => a.append(&mut b),
+            (ChannelData::F64(a), ChannelData::F64(mut b)) => a.append(&mut b),
+            (ChannelData::ComplexF32(a), ChannelData::ComplexF32(mut b)) => a.append(&mut b),
+            (ChannelData::ComplexF64(a), ChannelData::ComplexF64(mut b)) => a.append(&mut b),
+            (ChannelData::Boolean(a), ChannelData::Boolean(mut b)) => a.append(&mut b),
+            (ChannelData::TimeStamp(a), ChannelData::TimeStamp(mut b)) => a.append(&mut b),
+            (ChannelData::String(a), ChannelData::String(mut b)) => a.append(&mut b),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Decodes `count` values of a fixed size type stored back to back in `bytes`.
+    fn extend_fixed<B: ByteOrder>(&mut self, bytes: &[u8], count: usize) {
+        match self {
+            ChannelData::I8(values) => values.extend(bytes[..count].iter().map(|&b| b as i8)),
+            ChannelData::I16(values) => values.extend(chunks(bytes, 2, count).map(B::read_i16)),
+            ChannelData::I32(values) => values.extend(chunks(bytes, 4, count).map(B::read_i32)),
+            ChannelData::I64(values) => values.extend(chunks(bytes, 8, count).map(B::read_i64)),
+            ChannelData::U8(values) => values.extend_from_slice(&bytes[..count]),
+            ChannelData::U16(values) => values.extend(chunks(bytes, 2, count).map(B::read_u16)),
+            ChannelData::U32(values) => values.extend(chunks(bytes, 4, count).map(B::read_u32)),
+            ChannelData::U64(values) => values.extend(chunks(bytes, 8, count).map(B::read_u64)),
+            ChannelData::F32(values) => values.extend(chunks(bytes, 4, count).map(B::read_f32)),
+            ChannelData::F64(values) => values.extend(chunks(bytes, 8, count).map(B::read_f64)),
+            ChannelData::ComplexF32(values) => values.extend(
+                chunks(bytes, 8, count).map(|b| (B::read_f32(&b[..4]), B::read_f32(&b[4..]))),
+            ),
+            ChannelData::ComplexF64(values) => values.extend(
+                chunks(bytes, 16, count).map(|b| (B::read_f64(&b[..8]), B::read_f64(&b[8..]))),
+            ),
+            ChannelData::Boolean(values) => values.extend(bytes[..count].iter().map(|&b| b != 0)),
+            ChannelData::TimeStamp(values) => {
+                values.extend(chunks(bytes, 16, count).map(read_timestamp::<B>))
+            }
+            ChannelData::String(_) => unreachable!("strings have no fixed size"),
+        }
+    }
+
+    /// Decodes a string chunk: `count` u32 end offsets followed by the concatenated strings.
+    fn extend_strings<B: ByteOrder>(
+        &mut self,
+        bytes: &[u8],
+        count: usize,
+        strings: StringDecoding,
+    ) -> Result<(), StringChunkError> {
+        let values = match self {
+            ChannelData::String(values) => values,
+            _ => unreachable!("not a string channel"),
+        };
+        let table_size = count.saturating_mul(4);
+        if bytes.len() < table_size {
+            return Err(StringChunkError::Malformed(format!(
+                "string offset table of {} bytes is truncated",
+                table_size
+            )));
+        }
+        let (table, text) = bytes.split_at(table_size);
+        let mut start = 0;
+        for end in chunks(table, 4, count).map(B::read_u32) {
+            let end = end as usize;
+            if end < start || end > text.len() {
+                return Err(StringChunkError::Malformed(format!(
+                    "string end offset {} is out of range",
+                    end
+                )));
+            }
+            let value = decode_string(&text[start..end], strings)
+                .map_err(|valid| StringChunkError::InvalidUtf8(table_size + start + valid))?;
+            values.push(value);
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+/// Why a string chunk cannot be decoded.
+enum StringChunkError {
+    Malformed(String),
+    /// Position of the first invalid byte within the chunk.
+    InvalidUtf8(usize),
+}
+
+fn chunks(bytes: &[u8], size: usize, count: usize) -> std::slice::ChunksExact<'_, u8> {
+    bytes[..size * count].chunks_exact(size)
+}
+
+/// A timestamp is stored as a 128 bit number with the seconds in the upper half.
+fn read_timestamp<B: ByteOrder>(bytes: &[u8]) -> TdmsTimestamp {
+    let value = B::read_u128(bytes);
+    TdmsTimestamp {
+        seconds: (value >> 64) as i64,
+        fractions: value as u64,
+    }
+}
+
+/// Reads a single value of a fixed size `datatype`, as stored for properties.
+///
+/// Returns `None` after skipping the value if values of the type cannot be
+/// decoded (extended precision floats), fails for types without a fixed size.
+pub fn read_value<T: Read>(
+    reader: &mut T,
+    datatype: &TdmsDataType,
+    big_endian: bool,
+) -> Result<Option<ChannelData>, TdmsParseError> {
+    let size = value_size(datatype).ok_or_else(|| TdmsParseError::GeneralError {
+        message: format!("cannot read a single {:?} value", datatype),
+    })?;
+    let mut bytes = vec![0; size as usize];
+    reader.read_exact(&mut bytes)?;
+    let mut data = match ChannelData::new(datatype) {
+        Some(data) if size > 0 => data,
+        _ => return Ok(None),
+    };
+    if big_endian {
+        data.extend_fixed::<BigEndian>(&bytes, 1);
+    } else {
+        data.extend_fixed::<LittleEndian>(&bytes, 1);
+    }
+    Ok(Some(data))
+}
+
+/// Size in bytes of a value of `datatype`, `None` for strings and types
+/// without a fixed size.
+pub fn value_size(datatype: &TdmsDataType) -> Option<u64> {
+    match datatype {
+        TdmsDataType::Void => Some(0),
+        TdmsDataType::I8 | TdmsDataType::U8 | TdmsDataType::Boolean => Some(1),
+        TdmsDataType::I16 | TdmsDataType::U16 => Some(2),
+        TdmsDataType::I32
+        | TdmsDataType::U32
+        | TdmsDataType::SingleFloat
+        | TdmsDataType::SingleFloatWithUnit => Some(4),
+        TdmsDataType::I64
+        | TdmsDataType::U64
+        | TdmsDataType::DoubleFloat
+        | TdmsDataType::DoubleFloatWithUnit
+        | TdmsDataType::ComplexSingleFloat => Some(8),
+        TdmsDataType::ExtendedFloat
+        | TdmsDataType::ExtendedFloatWithUnit
+        | TdmsDataType::TimeStamp
+        | TdmsDataType::ComplexDoubleFloat => Some(16),
+        TdmsDataType::String | TdmsDataType::FixedPoint | TdmsDataType::DAQmxRawData => None,
+    }
+}
+
+/// Position of a channel's values within a chunk of the raw data of a segment.
+#[derive(Debug, Clone)]
+pub struct ChannelLayout {
+    pub path: String,
+    pub datatype: TdmsDataType,
+    /// Values of the channel per chunk.
+    pub number_of_values: u64,
+    /// Bytes of a value, 0 for strings.
+    pub value_size: u64,
+    /// Bytes of the channel per chunk.
+    pub size: u64,
+    /// Offset of the channel's first byte from the start of a chunk, or from
+    /// the start of a row for interleaved data.
+    pub offset: u64,
+}
+
+/// Layout of the raw data of a segment.
+///
+/// The raw data consists of `chunk_count` repetitions of a chunk holding the
+/// values of every channel of the object list that has raw data. Without
+/// interleaving a chunk holds the values of each channel back to back, with
+/// interleaving it holds `number_of_values` rows of one value per channel.
+#[derive(Debug, Clone)]
+pub struct SegmentLayout {
+    /// Absolute file position of the first raw data byte.
+    pub raw_data_pos: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+    pub interleaved: bool,
+    pub big_endian: bool,
+    /// Decoding of the values of string channels.
+    pub strings: StringDecoding,
+    pub channels: Vec<ChannelLayout>,
+}
+
+impl SegmentLayout {
+    /// The layout of the segment at `segment_pos` with its resolved metadata,
+    /// see [`SegmentReader`].
+    pub fn new(
+        segment_pos: u64,
+        lead_in: &LeadIn,
+        metadata: &Metadata,
+        file_length: u64,
+    ) -> Result<Self, TdmsParseError> {
+        let raw_data = DataSegment::new(segment_pos, lead_in, file_length);
+        let interleaved = lead_in.is_raw_data_in_segment_interleaved;
+        let mut layout = SegmentLayout {
+            raw_data_pos: raw_data.absolute_pos,
+            chunk_size: 0,
+            chunk_count: 0,
+            interleaved,
+            big_endian: lead_in.is_toc_big_endian,
+            strings: StringDecoding::Strict,
+            channels: vec![],
+        };
+        if !lead_in.contains_raw_data {
+            return Ok(layout);
+        }
+        // the raw data of a segment that is still being written may hold less than a chunk
+        let complete_size = if lead_in.next_segment_offset == INCOMPLETE_SEGMENT_OFFSET {
+            None
+        } else {
+            Some(raw_data.size)
+        };
+
+        for object in metadata.objects.iter() {
+            let unsupported = |message: &str| TdmsParseError::UnsupportedRawData {
+                path: object.path.clone(),
+                message: message.to_string(),
+            };
+            let index = match &object.raw_data_index {
+                RawDataIndex::NewDataIndex(index) => index,
+                RawDataIndex::NoRawData => continue,
+                RawDataIndex::Daqmx(_) | RawDataIndex::DaqmxDigitalLine(_) => {
+                    return Err(unsupported("DAQmx raw data"))
+                }
+                RawDataIndex::SameAsPrevious => {
+                    return Err(unsupported("unresolved raw data index"))
+                }
+            };
+            let number_of_values = index
+                .chunk_size
+                .checked_mul(index.array_dimension as u64)
+                .ok_or_else(|| unsupported("number of values out of range"))?;
+            let (value_size, size) = match value_size(&index.datatype) {
+                Some(value_size) => (
+                    value_size,
+                    value_size
+                        .checked_mul(number_of_values)
+                        .ok_or_else(|| unsupported("channel size out of range"))?,
+                ),
+                None if index.datatype == TdmsDataType::String && !interleaved => {
+                    (0, index.total_size_bytes)
+                }
+                None => return Err(unsupported(&format!("{:?} values", index.datatype))),
+            };
+            let offset = if interleaved {
+                if let Some(first) = layout.channels.first() {
+                    if first.number_of_values != number_of_values {
+                        return Err(unsupported("interleaved channels of different lengths"));
+                    }
+                }
+                layout.row_size()
+            } else {
+                layout.chunk_size
+            };
+            layout.chunk_size = layout
+                .chunk_size
+                .checked_add(size)
+                .filter(|&chunk_size| complete_size.is_none_or(|raw| chunk_size <= raw))
+                .ok_or_else(|| {
+                    unsupported(&format!(
+                        "chunk exceeds the {} raw data bytes of the segment",
+                        raw_data.size
+                    ))
+                })?;
+            trace!("{} value size {} at {}", object.path, value_size, offset);
+            layout.channels.push(ChannelLayout {
+                path: object.path.clone(),
+                datatype: index.datatype.clone(),
+                number_of_values,
+                value_size,
+                size,
+                offset,
+            });
+        }
+
+        if let Some(chunk_count) = raw_data.size.checked_div(layout.chunk_size) {
+            layout.chunk_count = chunk_count;
+            if raw_data.size % layout.chunk_size != 0 {
+                warn!(
+                    "segment at {} has {} raw data bytes, not a multiple of the chunk size {}",
+                    segment_pos, raw_data.size, layout.chunk_size
+                );
+            }
+        }
+        Ok(layout)
+    }
+
+    pub fn channel(&self, path: &str) -> Option<&ChannelLayout> {
+        self.channels.iter().find(|channel| channel.path == path)
+    }
+
+    /// Bytes of one row of interleaved data.
+    fn row_size(&self) -> u64 {
+        self.channels.iter().map(|channel| channel.value_size).sum()
+    }
+
+    /// Reads and decodes the values of the channel `path` from all chunks,
+    /// `None` if the channel has no raw data in the segment.
+    pub fn read_channel<T: Read + Seek>(
+        &self,
+        reader: &mut T,
+        path: &str,
+    ) -> Result<Option<ChannelData>, TdmsParseError> {
+        let channel = match self.channel(path) {
+            Some(channel) => channel,
+            None => return Ok(None),
+        };
+        let mut data = ChannelData::new(&channel.datatype).ok_or_else(|| {
+            TdmsParseError::UnsupportedRawData {
+                path: path.to_string(),
+                message: format!("{:?} values", channel.datatype),
+            }
+        })?;
+        let count = channel.number_of_values as usize;
+        let row_size = self.row_size();
+
+        for chunk in 0..self.chunk_count {
+            let chunk_pos = self.raw_data_pos + chunk * self.chunk_size;
+            let bytes = if self.interleaved {
+                let rows = read_at(reader, chunk_pos, self.chunk_size)?;
+                let value = channel.offset as usize..(channel.offset + channel.value_size) as usize;
+                rows.chunks_exact(row_size as usize)
+                    .flat_map(|row| row[value.clone()].iter().copied())
+                    .collect()
+            } else {
+                read_at(reader, chunk_pos + channel.offset, channel.size)?
+            };
+
+            if channel.datatype == TdmsDataType::String {
+                let result = if self.big_endian {
+                    data.extend_strings::<BigEndian>(&bytes, count, self.strings)
+                } else {
+                    data.extend_strings::<LittleEndian>(&bytes, count, self.strings)
+                };
+                result.map_err(|error| match error {
+                    StringChunkError::Malformed(message) => TdmsParseError::UnsupportedRawData {
+                        path: path.to_string(),
+                        message,
+                    },
+                    StringChunkError::InvalidUtf8(offset) => TdmsParseError::InvalidUtf8 {
+                        path: path.to_string(),
+                        offset: chunk_pos + channel.offset + offset as u64,
+                    },
+                })?;
+            } else if self.big_endian {
+                data.extend_fixed::<BigEndian>(&bytes, count);
+            } else {
+                data.extend_fixed::<LittleEndian>(&bytes, count);
+            }
+        }
+        Ok(Some(data))
+    }
+}
+
+/// Reads `size` bytes at `pos`. The buffer grows with the bytes actually read,
+/// so a size taken from corrupt metadata cannot exhaust the memory.
+fn read_at<T: Read + Seek>(reader: &mut T, pos: u64, size: u64) -> Result<Vec<u8>, TdmsParseError> {
+    reader.seek(SeekFrom::Start(pos))?;
+    let mut bytes = vec![];
+    reader.take(size).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < size {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(bytes)
+}
+
+/// A channel of a TDMS file with the values of all segments.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub path: String,
+    pub data: ChannelData,
+}
+
+/// Reads the raw data of every channel of a TDMS file, in the order the
+/// channels first appear. Not limited to HVDS files.
+pub fn read_channels(path: &Path) -> Result<Vec<Channel>, TdmsParseError> {
+    read_channels_with(path, StringDecoding::Strict)
+}
+
+/// Like [`read_channels`], decoding names, properties and string values with `strings`.
+pub fn read_channels_with(
+    path: &Path,
+    strings: StringDecoding,
+) -> Result<Vec<Channel>, TdmsParseError> {
+    let file = File::open(path)?;
+    let file_length = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut segment_reader = SegmentReader::with_string_decoding(strings);
+    let mut channels: Vec<Channel> = vec![];
+
+    let mut pos = 0;
+    while has_lead_in_at(pos, file_length) {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (lead_in, metadata) = segment_reader.read_header(&mut reader)?;
+        let mut layout = SegmentLayout::new(pos, &lead_in, &metadata, file_length)?;
+        layout.strings = strings;
+        for channel_layout in layout.channels.iter() {
+            let data = match layout.read_channel(&mut reader, &channel_layout.path)? {
+                Some(data) => data,
+                None => continue,
+            };
+            match channels.iter_mut().find(|c| c.path == channel_layout.path) {
+                Some(channel) => {
+                    if !channel.data.append(data) {
+                        return Err(TdmsParseError::UnsupportedRawData {
+                            path: channel.path.clone(),
+                            message: "data type changes between segments".to_string(),
+                        });
+                    }
+                }
+                None => channels.push(Channel {
+                    path: channel_layout.path.clone(),
+                    data,
+                }),
+            }
+        }
+
+        match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) => pos = next_pos,
+            None => break,
+        }
+    }
+    Ok(channels)
+}
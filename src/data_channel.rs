@@ -0,0 +1,119 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::ByteRange;
+use crate::tdms_lead_in::{LeadIn, LEAD_IN_SIZE};
+use crate::tdms_segment::INCOMPLETE_SEGMENT_OFFSET;
+
+/// Raw data of the Data channel within one segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataSegment {
+    /// Absolute file position of the first raw data byte.
+    #[serde(rename = "Offset")]
+    pub absolute_pos: u64,
+
+    #[serde(rename = "Size")]
+    pub size: u64,
+}
+
+impl DataSegment {
+    /// The raw data of the segment starting at `segment_pos`. The raw data of a
+    /// segment that is still being written extends to the end of the file.
+    pub fn new(segment_pos: u64, lead_in: &LeadIn, file_length: u64) -> Self {
+        let absolute_pos = (segment_pos + LEAD_IN_SIZE).saturating_add(lead_in.raw_data_offset);
+        let size = if lead_in.next_segment_offset == INCOMPLETE_SEGMENT_OFFSET {
+            file_length.saturating_sub(absolute_pos)
+        } else {
+            // LeadIn::read ensures the raw data offset is within the segment
+            lead_in.next_segment_offset - lead_in.raw_data_offset
+        };
+        DataSegment { absolute_pos, size }
+    }
+}
+
+/// Maps offsets within the Data channel, as stored in the Index channel, to
+/// absolute file positions.
+///
+/// The Data channel is split over several segments, each with its own lead-in
+/// and metadata between the raw data, so the channel offsets are only
+/// contiguous within a segment.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<DataSegment>", into = "Vec<DataSegment>")]
+pub struct DataChannelMap {
+    segments: Vec<DataSegment>,
+    /// Channel offset of the first byte of every segment.
+    starts: Vec<u64>,
+}
+
+impl DataChannelMap {
+    pub fn push(&mut self, segment: DataSegment) {
+        self.starts.push(self.len());
+        self.segments.push(segment);
+    }
+
+    pub fn segments(&self) -> &[DataSegment] {
+        &self.segments
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Size of the Data channel in bytes.
+    pub fn len(&self) -> u64 {
+        match (self.starts.last(), self.segments.last()) {
+            (Some(start), Some(segment)) => start + segment.size,
+            _ => 0,
+        }
+    }
+
+    /// Absolute file ranges of the channel bytes `range`, split at segment boundaries.
+    ///
+    /// Bytes past the end of the last segment are left out; a range starting
+    /// there maps to an empty range at the end of the last segment.
+    pub fn map_range(&self, range: Range<u64>) -> Vec<ByteRange> {
+        let mut ranges = vec![];
+        if self.segments.is_empty() {
+            return ranges;
+        }
+
+        let mut pos = range.start;
+        let mut i = self.starts.partition_point(|&start| start <= pos) - 1;
+        loop {
+            let segment = &self.segments[i];
+            let segment_end = self.starts[i] + segment.size;
+            let last = i + 1 == self.segments.len();
+            let start = pos.min(segment_end);
+            let end = range.end.min(segment_end);
+            if end > start || ranges.is_empty() {
+                ranges.push(ByteRange {
+                    offset: segment.absolute_pos + (start - self.starts[i]),
+                    size: end.saturating_sub(start),
+                });
+            }
+            if last || end >= range.end {
+                break;
+            }
+            pos = end;
+            i += 1;
+        }
+        ranges
+    }
+}
+
+impl From<Vec<DataSegment>> for DataChannelMap {
+    fn from(segments: Vec<DataSegment>) -> Self {
+        let mut map = DataChannelMap::default();
+        for segment in segments {
+            map.push(segment);
+        }
+        map
+    }
+}
+
+impl From<DataChannelMap> for Vec<DataSegment> {
+    fn from(map: DataChannelMap) -> Self {
+        map.segments
+    }
+}
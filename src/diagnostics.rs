@@ -0,0 +1,533 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::{Data, FileMetadata};
+use crate::unified_time::epoch_nanos;
+
+/// Maximum number of individual findings listed per category, the counts are always complete.
+pub const MAX_REPORTED: usize = 1000;
+
+/// Intervals shorter than this fraction of the median frame period are reported as unusual.
+pub const SHORT_INTERVAL_FACTOR: f64 = 0.5;
+
+/// Intervals longer than this multiple of the median frame period are reported as unusual.
+pub const LONG_INTERVAL_FACTOR: f64 = 1.5;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Diagnostics {
+    #[serde(rename = "FrameNumbers")]
+    pub frame_numbers: FrameNumberReport,
+
+    #[serde(rename = "Timestamps", default)]
+    pub timestamps: TimestampReport,
+}
+
+impl Diagnostics {
+    /// Analyzes the frames of a stream.
+    ///
+    /// The index only keeps the start timestamps, so `end_timestamps` holds the
+    /// relative end timestamps of the last `end_timestamps.len()` rows of `data`.
+    /// The split window is only checked if the file has a `FutureTimeEvent` to
+    /// convert the relative timestamps with.
+    pub fn analyze(metadata: &FileMetadata, data: &Data, end_timestamps: &[u64]) -> Self {
+        let split = if metadata.reference_split_stop_time > 0 && metadata.future_time_event > 0 {
+            Some(SplitWindow {
+                future_time_event: metadata.future_time_event,
+                range: metadata.reference_split_start_time..=metadata.reference_split_stop_time,
+            })
+        } else {
+            None
+        };
+        Diagnostics {
+            frame_numbers: FrameNumberReport::analyze(&data.frame_number),
+            timestamps: TimestampReport::analyze(&data.timestamp, end_timestamps, split),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.frame_numbers, self.timestamps)
+    }
+}
+
+/// Consistency of the `FrameNumber` column of the header channel.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FrameNumberReport {
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+
+    #[serde(rename = "FirstFrameNumber")]
+    pub first_frame_number: Option<u32>,
+
+    #[serde(rename = "LastFrameNumber")]
+    pub last_frame_number: Option<u32>,
+
+    /// Number of frame numbers missing in the sequence (dropped frames).
+    #[serde(rename = "DroppedFrames")]
+    pub dropped_frames: u64,
+
+    #[serde(rename = "GapCount")]
+    pub gap_count: usize,
+
+    #[serde(rename = "Gaps")]
+    pub gaps: Vec<FrameNumberGap>,
+
+    #[serde(rename = "DuplicateCount")]
+    pub duplicate_count: usize,
+
+    #[serde(rename = "Duplicates")]
+    pub duplicates: Vec<FrameOccurrence>,
+
+    #[serde(rename = "OutOfOrderCount")]
+    pub out_of_order_count: usize,
+
+    #[serde(rename = "OutOfOrder")]
+    pub out_of_order: Vec<FrameOccurrence>,
+
+    /// Rows at which the u32 frame counter wrapped around to 0.
+    #[serde(rename = "WrapArounds")]
+    pub wrap_arounds: Vec<usize>,
+}
+
+/// Frame numbers `first..=last` are missing before the frame in `row`.
+/// `first` is greater than `last` if the gap spans a wrap-around of the counter.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameNumberGap {
+    #[serde(rename = "Row")]
+    pub row: usize,
+
+    #[serde(rename = "First")]
+    pub first: u32,
+
+    #[serde(rename = "Last")]
+    pub last: u32,
+
+    #[serde(rename = "Missing")]
+    pub missing: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameOccurrence {
+    #[serde(rename = "Row")]
+    pub row: usize,
+
+    #[serde(rename = "FrameNumber")]
+    pub frame_number: u32,
+
+    /// The frame number preceding it in sequence.
+    #[serde(rename = "Previous")]
+    pub previous: u32,
+}
+
+impl FrameNumberReport {
+    /// Checks that every frame number is its predecessor + 1 (modulo 2^32).
+    ///
+    /// A frame whose number lies behind the last in-sequence frame (by more than
+    /// half the counter range) is counted as out of order and does not move the
+    /// sequence, so one late frame does not produce a gap and a duplicate. If the
+    /// next frame follows the out-of-order one, the counter has been reset and the
+    /// sequence continues from there.
+    pub fn analyze(frame_numbers: &[u32]) -> Self {
+        let mut report = FrameNumberReport {
+            frame_count: frame_numbers.len(),
+            first_frame_number: frame_numbers.first().copied(),
+            last_frame_number: frame_numbers.last().copied(),
+            ..Default::default()
+        };
+
+        let mut last = match frame_numbers.first() {
+            Some(&first) => first,
+            None => return report,
+        };
+        // the last out-of-order frame number, a possible reset of the counter
+        let mut behind = None;
+        for (row, &frame_number) in frame_numbers.iter().enumerate().skip(1) {
+            let distance = frame_number.wrapping_sub(last);
+            let occurrence = FrameOccurrence {
+                row,
+                frame_number,
+                previous: last,
+            };
+            if distance == 0 {
+                report.duplicate_count += 1;
+                push_limited(&mut report.duplicates, occurrence);
+                continue;
+            }
+            if distance > u32::MAX / 2 {
+                if behind.map(|number: u32| number.wrapping_add(1)) == Some(frame_number) {
+                    last = frame_number;
+                    behind = None;
+                    continue;
+                }
+                report.out_of_order_count += 1;
+                push_limited(&mut report.out_of_order, occurrence);
+                behind = Some(frame_number);
+                continue;
+            }
+
+            if distance > 1 {
+                report.gap_count += 1;
+                report.dropped_frames += (distance - 1) as u64;
+                push_limited(
+                    &mut report.gaps,
+                    FrameNumberGap {
+                        row,
+                        first: last.wrapping_add(1),
+                        last: frame_number.wrapping_sub(1),
+                        missing: (distance - 1) as u64,
+                    },
+                );
+            }
+            if frame_number < last {
+                push_limited(&mut report.wrap_arounds, row);
+            }
+            last = frame_number;
+            behind = None;
+        }
+
+        report
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.gap_count == 0 && self.duplicate_count == 0 && self.out_of_order_count == 0
+    }
+}
+
+fn push_limited<T>(items: &mut Vec<T>, item: T) {
+    if items.len() < MAX_REPORTED {
+        items.push(item);
+    }
+}
+
+impl fmt::Display for FrameNumberReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.first_frame_number, self.last_frame_number) {
+            (Some(first), Some(last)) => writeln!(
+                f,
+                "  frame numbers: {} frames, {} .. {}",
+                self.frame_count, first, last
+            )?,
+            _ => return writeln!(f, "  frame numbers: no frames"),
+        }
+        if self.is_consistent() && self.wrap_arounds.is_empty() {
+            return writeln!(f, "    continuous");
+        }
+
+        writeln!(
+            f,
+            "    {} gaps ({} dropped frames), {} duplicates, {} out of order, {} wrap-arounds",
+            self.gap_count,
+            self.dropped_frames,
+            self.duplicate_count,
+            self.out_of_order_count,
+            self.wrap_arounds.len()
+        )?;
+        for gap in self.gaps.iter().take(10) {
+            writeln!(
+                f,
+                "    gap at row {}: {} .. {} missing",
+                gap.row, gap.first, gap.last
+            )?;
+        }
+        for duplicate in self.duplicates.iter().take(10) {
+            writeln!(
+                f,
+                "    duplicate at row {}: {}",
+                duplicate.row, duplicate.frame_number
+            )?;
+        }
+        for frame in self.out_of_order.iter().take(10) {
+            writeln!(
+                f,
+                "    out of order at row {}: {} after {}",
+                frame.row, frame.frame_number, frame.previous
+            )?;
+        }
+        for row in self.wrap_arounds.iter().take(10) {
+            writeln!(f, "    wrap-around at row {}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plausibility of the relative start and end timestamps of the timestamp channel.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TimestampReport {
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+
+    #[serde(rename = "FirstTimestamp")]
+    pub first_timestamp: Option<u64>,
+
+    #[serde(rename = "LastTimestamp")]
+    pub last_timestamp: Option<u64>,
+
+    /// Start timestamps not after the start timestamp of the previous frame.
+    #[serde(rename = "NonMonotonicCount")]
+    pub non_monotonic_count: usize,
+
+    #[serde(rename = "NonMonotonic")]
+    pub non_monotonic: Vec<TimestampFinding>,
+
+    #[serde(rename = "EndBeforeStartCount")]
+    pub end_before_start_count: usize,
+
+    #[serde(rename = "EndBeforeStart")]
+    pub end_before_start: Vec<TimestampFinding>,
+
+    /// Start timestamps outside `SplitStartTime..=SplitStopTime`. Timestamp and
+    /// reference of these findings are nanoseconds since the epoch.
+    #[serde(rename = "OutsideSplitCount")]
+    pub outside_split_count: usize,
+
+    #[serde(rename = "OutsideSplit")]
+    pub outside_split: Vec<TimestampFinding>,
+
+    /// Statistics of the intervals between consecutive, monotonic start timestamps.
+    #[serde(rename = "Period")]
+    pub period: Option<PeriodStatistics>,
+
+    #[serde(rename = "UnusualIntervalCount")]
+    pub unusual_interval_count: usize,
+
+    #[serde(rename = "UnusualIntervals")]
+    pub unusual_intervals: Vec<TimestampFinding>,
+}
+
+/// `SplitStartTime..=SplitStopTime` of a file, in nanoseconds since the epoch
+/// like the `FutureTimeEvent` the relative timestamps are converted with.
+#[derive(Debug, Clone)]
+pub struct SplitWindow {
+    pub future_time_event: u64,
+    pub range: RangeInclusive<u64>,
+}
+
+/// A suspicious timestamp of the frame in `row`, `reference` is the value it
+/// was checked against (previous start, own start or split boundary).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimestampFinding {
+    #[serde(rename = "Row")]
+    pub row: usize,
+
+    #[serde(rename = "Timestamp")]
+    pub timestamp: u64,
+
+    #[serde(rename = "Reference")]
+    pub reference: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodStatistics {
+    #[serde(rename = "Min")]
+    pub min: u64,
+
+    #[serde(rename = "Max")]
+    pub max: u64,
+
+    #[serde(rename = "Mean")]
+    pub mean: f64,
+
+    #[serde(rename = "Median")]
+    pub median: u64,
+
+    /// Standard deviation of the intervals.
+    #[serde(rename = "Jitter")]
+    pub jitter: f64,
+}
+
+impl TimestampReport {
+    /// Checks the start timestamps of a stream. Intervals are reported as
+    /// unusual if they differ from the median period by the interval factors.
+    pub fn analyze(
+        start_timestamps: &[u64],
+        end_timestamps: &[u64],
+        split: Option<SplitWindow>,
+    ) -> Self {
+        let mut report = TimestampReport {
+            frame_count: start_timestamps.len(),
+            first_timestamp: start_timestamps.first().copied(),
+            last_timestamp: start_timestamps.last().copied(),
+            ..Default::default()
+        };
+
+        let first_end_row = start_timestamps.len().saturating_sub(end_timestamps.len());
+        for (i, &end) in end_timestamps.iter().enumerate() {
+            let row = first_end_row + i;
+            if end < start_timestamps[row] {
+                report.end_before_start_count += 1;
+                push_limited(
+                    &mut report.end_before_start,
+                    TimestampFinding {
+                        row,
+                        timestamp: end,
+                        reference: start_timestamps[row],
+                    },
+                );
+            }
+        }
+
+        if let Some(split) = split {
+            let range = &split.range;
+            for (row, &relative) in start_timestamps.iter().enumerate() {
+                // a time beyond the supported range is past any split
+                let timestamp = epoch_nanos(split.future_time_event, relative).unwrap_or(u64::MAX);
+                if !range.contains(&timestamp) {
+                    let reference = if timestamp < *range.start() {
+                        *range.start()
+                    } else {
+                        *range.end()
+                    };
+                    report.outside_split_count += 1;
+                    push_limited(
+                        &mut report.outside_split,
+                        TimestampFinding {
+                            row,
+                            timestamp,
+                            reference,
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut intervals = vec![];
+        for (row, pair) in start_timestamps.windows(2).enumerate() {
+            let finding = TimestampFinding {
+                row: row + 1,
+                timestamp: pair[1],
+                reference: pair[0],
+            };
+            if pair[1] <= pair[0] {
+                report.non_monotonic_count += 1;
+                push_limited(&mut report.non_monotonic, finding);
+            } else {
+                intervals.push(finding);
+            }
+        }
+        if intervals.is_empty() {
+            return report;
+        }
+
+        let mut periods: Vec<u64> = intervals.iter().map(interval).collect();
+        periods.sort_unstable();
+        let median = periods[periods.len() / 2];
+        let mean = periods.iter().map(|&p| p as f64).sum::<f64>() / periods.len() as f64;
+        let variance = periods
+            .iter()
+            .map(|&p| (p as f64 - mean).powi(2))
+            .sum::<f64>()
+            / periods.len() as f64;
+        report.period = Some(PeriodStatistics {
+            min: periods[0],
+            max: periods[periods.len() - 1],
+            mean,
+            median,
+            jitter: variance.sqrt(),
+        });
+
+        let short = median as f64 * SHORT_INTERVAL_FACTOR;
+        let long = median as f64 * LONG_INTERVAL_FACTOR;
+        for finding in intervals {
+            let period = interval(&finding) as f64;
+            if period < short || period > long {
+                report.unusual_interval_count += 1;
+                push_limited(&mut report.unusual_intervals, finding);
+            }
+        }
+
+        report
+    }
+
+    /// Takes over the end-before-start findings of a previous report for the
+    /// first `rows` rows, whose end timestamps are no longer available.
+    pub fn carry_over(&mut self, previous: &TimestampReport, rows: usize) {
+        let mut findings: Vec<TimestampFinding> = previous
+            .end_before_start
+            .iter()
+            .filter(|finding| finding.row < rows)
+            .cloned()
+            .collect();
+        let dropped = previous.end_before_start.len() - findings.len();
+        self.end_before_start_count += previous.end_before_start_count - dropped;
+
+        findings.append(&mut self.end_before_start);
+        findings.truncate(MAX_REPORTED);
+        self.end_before_start = findings;
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.non_monotonic_count == 0
+            && self.end_before_start_count == 0
+            && self.outside_split_count == 0
+            && self.unusual_interval_count == 0
+    }
+}
+
+fn interval(finding: &TimestampFinding) -> u64 {
+    finding.timestamp - finding.reference
+}
+
+impl fmt::Display for TimestampReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.first_timestamp, self.last_timestamp) {
+            (Some(first), Some(last)) => writeln!(
+                f,
+                "  timestamps: {} frames, {} .. {}",
+                self.frame_count, first, last
+            )?,
+            _ => return writeln!(f, "  timestamps: no frames"),
+        }
+        if let Some(period) = &self.period {
+            writeln!(
+                f,
+                "    period min {} / max {} / mean {:.1} / median {}, jitter {:.1}",
+                period.min, period.max, period.mean, period.median, period.jitter
+            )?;
+        }
+        if self.is_consistent() {
+            return writeln!(f, "    plausible");
+        }
+
+        writeln!(
+            f,
+            "    {} non-monotonic, {} end before start, {} outside the split, {} unusual intervals",
+            self.non_monotonic_count,
+            self.end_before_start_count,
+            self.outside_split_count,
+            self.unusual_interval_count
+        )?;
+        for finding in self.non_monotonic.iter().take(10) {
+            writeln!(
+                f,
+                "    non-monotonic at row {}: {} after {}",
+                finding.row, finding.timestamp, finding.reference
+            )?;
+        }
+        for finding in self.end_before_start.iter().take(10) {
+            writeln!(
+                f,
+                "    end before start at row {}: {} < {}",
+                finding.row, finding.timestamp, finding.reference
+            )?;
+        }
+        for finding in self.outside_split.iter().take(10) {
+            writeln!(
+                f,
+                "    outside the split at row {}: {} (boundary {})",
+                finding.row, finding.timestamp, finding.reference
+            )?;
+        }
+        for finding in self.unusual_intervals.iter().take(10) {
+            writeln!(
+                f,
+                "    unusual interval at row {}: {}",
+                finding.row,
+                interval(finding)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::frame_reader::{Frame, FrameReader};
+use crate::output::Data;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::util::StringDecoding;
+
+/// Magic number starting every frame in a concatenated extraction file ("HVDF").
+pub const FRAME_MAGIC: &[u8; 4] = b"HVDF";
+
+/// Size of the framing header in a concatenated extraction file.
+pub const FRAME_HEADER_SIZE: usize = 28;
+
+/// Frames to extract, every criterion that is set has to match.
+#[derive(Debug, Default, Clone)]
+pub struct FrameSelection {
+    pub frame_numbers: Option<RangeInclusive<u32>>,
+    pub time_range: Option<RangeInclusive<u64>>,
+    pub state: Option<u32>,
+}
+
+impl FrameSelection {
+    /// A row missing from a column a criterion checks does not match.
+    pub fn matches(&self, data: &Data, row: usize) -> bool {
+        self.frame_numbers.as_ref().is_none_or(|range| {
+            data.frame_number
+                .get(row)
+                .is_some_and(|frame_number| range.contains(frame_number))
+        }) && self.time_range.as_ref().is_none_or(|range| {
+            data.timestamp
+                .get(row)
+                .is_some_and(|timestamp| range.contains(timestamp))
+        }) && self
+            .state
+            .is_none_or(|state| data.state.get(row) == Some(&state))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExtractTarget {
+    /// One `frame_<row>_<FrameNumber>.bin` file per frame in the directory.
+    Directory(PathBuf),
+    /// All frames in one file, each preceded by the framing header:
+    ///
+    /// | Bytes | Content                       |
+    /// | ----- | ----------------------------- |
+    /// | 4     | magic `HVDF`                  |
+    /// | 4     | frame number (u32)            |
+    /// | 4     | state (u32)                   |
+    /// | 8     | relative start timestamp (u64)|
+    /// | 8     | payload size in bytes (u64)   |
+    ///
+    /// All numbers are little-endian.
+    Concatenated(PathBuf),
+}
+
+/// Returns the rows of the index matching the selection.
+pub fn select_frames(data: &Data, selection: &FrameSelection) -> Vec<usize> {
+    (0..data.len())
+        .filter(|&row| selection.matches(data, row))
+        .collect()
+}
+
+/// Copies the payload of the given rows to the target.
+pub fn extract_frames<R: Read + Seek>(
+    reader: &mut FrameReader<R>,
+    rows: &[usize],
+    target: &ExtractTarget,
+) -> Result<(), TdmsParseError> {
+    let output_error = |path: &Path, e: std::io::Error| TdmsParseError::OutputError {
+        path: path.to_string_lossy().to_string(),
+        message: e.to_string(),
+    };
+
+    match target {
+        ExtractTarget::Directory(dir) => {
+            fs::create_dir_all(dir).map_err(|e| output_error(dir, e))?;
+            for &row in rows {
+                let frame = reader.frame_at(row)?;
+                let path = dir.join(format!("frame_{:08}_{}.bin", row, frame.frame_number));
+                fs::write(&path, frame.payload).map_err(|e| output_error(&path, e))?;
+            }
+        }
+        ExtractTarget::Concatenated(path) => {
+            let file = File::create(path).map_err(|e| output_error(path, e))?;
+            let mut writer = BufWriter::new(file);
+            for &row in rows {
+                let frame = reader.frame_at(row)?;
+                write_frame(&mut writer, &frame).map_err(|e| output_error(path, e))?;
+            }
+            writer.flush().map_err(|e| output_error(path, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the selected frames of a TDMS file.
+///
+/// The index is read from `index_path` if given, otherwise the file is parsed.
+/// Returns the number of extracted frames.
+pub fn extract(
+    path: &Path,
+    index_path: Option<&Path>,
+    selection: &FrameSelection,
+    target: &ExtractTarget,
+) -> Result<usize, TdmsParseError> {
+    extract_with(path, index_path, selection, target, StringDecoding::Strict)
+}
+
+/// Like [`extract`], decoding the strings of a parsed file with `strings`.
+pub fn extract_with(
+    path: &Path,
+    index_path: Option<&Path>,
+    selection: &FrameSelection,
+    target: &ExtractTarget,
+    strings: StringDecoding,
+) -> Result<usize, TdmsParseError> {
+    let mut reader = match index_path {
+        Some(index_path) => FrameReader::open_with_index(path, index_path)?,
+        None => FrameReader::open_with_string_decoding(path, strings)?,
+    };
+
+    let rows = select_frames(reader.data(), selection);
+    info!("extracting {} of {} frames", rows.len(), reader.len());
+
+    extract_frames(&mut reader, &rows, target)?;
+    Ok(rows.len())
+}
+
+fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> std::io::Result<()> {
+    writer.write_all(FRAME_MAGIC)?;
+    writer.write_u32::<LittleEndian>(frame.frame_number)?;
+    writer.write_u32::<LittleEndian>(frame.state)?;
+    writer.write_u64::<LittleEndian>(frame.timestamp)?;
+    writer.write_u64::<LittleEndian>(frame.payload.len() as u64)?;
+    writer.write_all(frame.payload)
+}
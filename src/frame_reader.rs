@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::vec;
+
+use crate::hvds::{index_with, read_output};
+use crate::output::{Data, FrameIssue};
+use crate::tdms_parse_error::TdmsParseError;
+use crate::util::StringDecoding;
+
+/// A frame as returned by the [`FrameReader`], the payload borrows the reader's buffer.
+#[derive(Debug)]
+pub struct Frame<'a> {
+    pub row: usize,
+    pub frame_number: u32,
+    pub state: u32,
+    pub timestamp: u64,
+    /// Set if the Index channel entry of the frame has negative or reversed offsets.
+    pub issue: Option<FrameIssue>,
+    pub payload: &'a [u8],
+}
+
+/// Random access to the frames of an HVDS file based on its parsed index tables.
+///
+/// Lookups by timestamp use a binary search if the timestamp column is sorted,
+/// as written by the logger, and scan all rows otherwise.
+pub struct FrameReader<R> {
+    reader: R,
+    data: Data,
+    rows_by_frame_number: HashMap<u32, usize>,
+    timestamps_sorted: bool,
+    buffer: Vec<u8>,
+}
+
+impl FrameReader<BufReader<File>> {
+    /// Parses the index tables of `path` and opens it for reading frames.
+    pub fn open(path: &Path) -> Result<Self, TdmsParseError> {
+        Self::open_with_string_decoding(path, StringDecoding::Strict)
+    }
+
+    /// Like [`FrameReader::open`], decoding the strings of the file with `strings`.
+    pub fn open_with_string_decoding(
+        path: &Path,
+        strings: StringDecoding,
+    ) -> Result<Self, TdmsParseError> {
+        let output = index_with(&path.to_string_lossy(), strings)?;
+        let reader = BufReader::new(File::open(path)?);
+        FrameReader::new(reader, output.file.stream.data)
+    }
+
+    /// Opens `path` using the tables of an existing `.hvds.json` index.
+    pub fn open_with_index(path: &Path, index_path: &Path) -> Result<Self, TdmsParseError> {
+        let output = read_output(index_path)?;
+        let reader = BufReader::new(File::open(path)?);
+        FrameReader::new(reader, output.file.stream.data).map_err(|e| {
+            TdmsParseError::GeneralError {
+                message: format!("invalid index {}: {}", index_path.display(), e),
+            }
+        })
+    }
+}
+
+impl<R: Read + Seek> FrameReader<R> {
+    /// Fails if the columns of `data` have different lengths, e.g. for an index
+    /// that was not written by this crate.
+    pub fn new(reader: R, data: Data) -> Result<Self, TdmsParseError> {
+        let lengths = [
+            data.frame_size.len(),
+            data.timestamp.len(),
+            data.state.len(),
+            data.frame_number.len(),
+        ];
+        if lengths.iter().any(|&length| length != data.len()) {
+            return Err(TdmsParseError::GeneralError {
+                message: format!(
+                    "frame columns of different lengths ({} byte offsets, {:?})",
+                    data.len(),
+                    lengths
+                ),
+            });
+        }
+
+        let mut rows_by_frame_number = HashMap::with_capacity(data.frame_number.len());
+        for (row, &frame_number) in data.frame_number.iter().enumerate() {
+            rows_by_frame_number.entry(frame_number).or_insert(row);
+        }
+        let timestamps_sorted = data.timestamp.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !timestamps_sorted {
+            warn!("timestamps are not sorted, lookups by time scan all frames");
+        }
+        Ok(FrameReader {
+            reader,
+            data,
+            rows_by_frame_number,
+            timestamps_sorted,
+            buffer: vec![],
+        })
+    }
+
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the frame in the given row of the index.
+    pub fn frame_at(&mut self, row: usize) -> Result<Frame<'_>, TdmsParseError> {
+        if row >= self.len() {
+            return Err(TdmsParseError::GeneralError {
+                message: format!("frame row {} out of range (0..{})", row, self.len()),
+            });
+        }
+
+        let ranges = self.data.frame_ranges(row);
+        let size: u64 = ranges.iter().map(|range| range.size).sum();
+        if size != self.data.frame_size[row] {
+            return Err(TdmsParseError::GeneralError {
+                message: format!(
+                    "byte ranges of frame row {} cover {} bytes instead of its size {}",
+                    row, size, self.data.frame_size[row]
+                ),
+            });
+        }
+
+        self.buffer.resize(size as usize, 0);
+        let mut pos = 0;
+        for range in ranges {
+            let end = pos + range.size as usize;
+            self.reader.seek(SeekFrom::Start(range.offset))?;
+            self.reader.read_exact(&mut self.buffer[pos..end])?;
+            pos = end;
+        }
+
+        Ok(Frame {
+            row,
+            frame_number: self.data.frame_number[row],
+            state: self.data.state[row],
+            timestamp: self.data.timestamp[row],
+            issue: self.data.frame_issue(row),
+            payload: &self.buffer,
+        })
+    }
+
+    /// Reads the first frame with the given `FrameNumber`.
+    pub fn frame(&mut self, frame_number: u32) -> Result<Option<Frame<'_>>, TdmsParseError> {
+        match self.rows_by_frame_number.get(&frame_number) {
+            Some(&row) => self.frame_at(row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// First row whose timestamp is not before `timestamp`, `len()` if there is none.
+    pub fn row_at_time(&self, timestamp: u64) -> usize {
+        let timestamps = &self.data.timestamp;
+        if self.timestamps_sorted {
+            timestamps.partition_point(|&t| t < timestamp)
+        } else {
+            timestamps
+                .iter()
+                .position(|&t| t >= timestamp)
+                .unwrap_or(timestamps.len())
+        }
+    }
+
+    /// Rows whose timestamp lies within `start..=end`, in index order.
+    pub fn rows_between(&self, start: u64, end: u64) -> Vec<usize> {
+        let timestamps = &self.data.timestamp;
+        if self.timestamps_sorted {
+            let first = timestamps.partition_point(|&t| t < start);
+            let last = timestamps.partition_point(|&t| t <= end);
+            (first..last.max(first)).collect()
+        } else {
+            (0..timestamps.len())
+                .filter(|&row| (start..=end).contains(&timestamps[row]))
+                .collect()
+        }
+    }
+
+    /// Iterates over the frames whose timestamp lies within `start..=end`.
+    pub fn frames_between(&mut self, start: u64, end: u64) -> Frames<'_, R> {
+        let rows = self.rows_between(start, end).into_iter();
+        Frames { reader: self, rows }
+    }
+
+    /// Iterates over all frames in index order.
+    pub fn frames(&mut self) -> Frames<'_, R> {
+        let rows = (0..self.len()).collect::<Vec<_>>().into_iter();
+        Frames { reader: self, rows }
+    }
+}
+
+/// Sequential access to a range of frames reusing the reader's buffer.
+///
+/// Since every frame borrows the shared buffer this is not an [`Iterator`];
+/// use `while let Some(frame) = frames.next_frame() { ... }`.
+pub struct Frames<'a, R> {
+    reader: &'a mut FrameReader<R>,
+    rows: vec::IntoIter<usize>,
+}
+
+impl<R: Read + Seek> Frames<'_, R> {
+    pub fn next_frame(&mut self) -> Option<Result<Frame<'_>, TdmsParseError>> {
+        let row = self.rows.next()?;
+        Some(self.reader.frame_at(row))
+    }
+
+    /// Number of frames left.
+    pub fn remaining(&self) -> usize {
+        self.rows.len()
+    }
+}
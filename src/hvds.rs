@@ -0,0 +1,762 @@
+use std::{fs::File, fs::OpenOptions, io::BufReader, io::Read, io::Seek, io::SeekFrom, ops::Range, path::Path, path::PathBuf, str::FromStr};
+
+use crate::channel_decoder::{ChannelData, ChannelLayout, SegmentLayout};
+use crate::data_channel::{DataChannelMap, DataSegment};
+use crate::diagnostics::Diagnostics;
+use crate::output::{
+    self, Annotations, Data, FileMetadata, FragmentedFrame, FrameIssue, InvalidFrame, Output,
+    Stream, StreamMetadata, SCHEMA_ID,
+};
+use crate::state_decoder::StateDecoder;
+use crate::tdms_datatype::TdmsDataType;
+use crate::tdms_lead_in::{has_lead_in_at, LeadIn};
+use crate::tdms_metadata::Metadata;
+use crate::tdms_object::TdmsObject;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::SegmentReader;
+use crate::unified_time::{epoch_nanos, split_unified_timestamp, to_iso8601};
+use crate::util::{write_json_atomically, StringDecoding};
+
+#[derive(Debug, Default, Clone)]
+pub struct IndexOptions {
+    /// Only used for the `RawFile` name in the index.
+    pub output_path: String,
+    /// Analyze the frame numbers and timestamps and add the `Diagnostics` section.
+    pub diagnostics: bool,
+    /// Adds the `StateSummary` section for streams with a state definition.
+    pub state_decoder: Option<StateDecoder>,
+    /// Adds the absolute UTC time of every frame based on `FutureTimeEvent`.
+    pub absolute_time: bool,
+    /// Whether strings with invalid UTF-8 fail the file or are decoded lossily.
+    pub string_decoding: StringDecoding,
+}
+
+/// Parses the HVDS channels of `path` and writes the `.hvds.json` header next to it.
+pub fn index_file(path: &Path, options: &IndexOptions) -> Result<Output, TdmsParseError> {
+    let path = path.to_str().ok_or_else(|| TdmsParseError::GeneralError {
+        message: format!("invalid file name {}", path.display()),
+    })?;
+
+    let (
+        file_metadata,
+        stream_metadata,
+        data_channel,
+        timestamps,
+        indices,
+        states,
+        frame_numbers,
+        annotations,
+    ) =
+        parse(path, options.string_decoding)?;
+    let end_timestamps = end_timestamps(&timestamps, timestamps.len() / 4);
+    let mut output = build_output(
+        path,
+        &options.output_path,
+        file_metadata,
+        stream_metadata,
+        data_channel,
+        timestamps,
+        indices,
+        states,
+        frame_numbers,
+        annotations,
+    )?;
+    if options.diagnostics {
+        output.file.stream.diagnostics = Some(Diagnostics::analyze(
+            &output.file.metadata,
+            &output.file.stream.data,
+            &end_timestamps,
+        ));
+    }
+
+    if let Some(decoder) = &options.state_decoder {
+        let stream = &mut output.file.stream;
+        stream.state_summary = decoder.summarize(&stream.metadata, &stream.data.state);
+    }
+    if options.absolute_time {
+        add_absolute_times(&mut output);
+    }
+
+    info!("Preparing to output");
+    write_output(&Path::new(path).with_extension("hvds.json"), &output)?;
+    Ok(output)
+}
+
+pub type ParsedHvds = (
+    FileMetadata,
+    StreamMetadata,
+    DataChannelMap,
+    Vec<u64>,
+    Vec<i64>,
+    Vec<u32>,
+    Vec<u32>,
+    Annotations,
+);
+
+pub fn parse(path: &str, strings: StringDecoding) -> Result<ParsedHvds, TdmsParseError> {
+    let mut file_metadata = FileMetadata::new();
+    let mut stream_metadata = StreamMetadata::new();
+
+    let file = OpenOptions::new().read(true).open(path)?;
+    let file_length = file.metadata()?.len();
+    let mut reader = BufReader::new(&file);
+    let mut segment_reader = SegmentReader::with_string_decoding(strings);
+
+    let mut data_channel = DataChannelMap::default();
+    let mut timestamps = vec![];
+    let mut indices = vec![];
+    let mut states = vec![];
+    let mut frame_numbers = vec![];
+    let mut annotations = Annotations::new();
+
+    // the logger writes the Data channel first, then Timestamp, Index and
+    // Header, but any order and segments holding several channels are read
+    let mut pos = 0;
+    while has_lead_in_at(pos, file_length) {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (lead_in, metadata) = segment_reader.read_header(&mut reader)?;
+        file_metadata.tdms_file_version = file_metadata.tdms_file_version.max(lead_in.version);
+        check_extract_metadata(
+            &metadata,
+            &segment_reader,
+            &mut file_metadata,
+            &mut stream_metadata,
+        )?;
+        let channels =
+            read_segment_channels(&mut reader, pos, file_length, &lead_in, &metadata, strings)?;
+        for segment in channels.data {
+            debug!("data channel byte offset = {:?}", segment.absolute_pos);
+            data_channel.push(segment);
+        }
+        timestamps.extend(channels.timestamps);
+        indices.extend(channels.indices);
+        states.extend(channels.states);
+        frame_numbers.extend(channels.frame_numbers);
+        extend_annotations(&mut annotations, channels.annotations);
+
+        match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) => pos = next_pos,
+            None => break,
+        }
+    }
+
+    Ok((
+        file_metadata,
+        stream_metadata,
+        data_channel,
+        timestamps,
+        indices,
+        states,
+        frame_numbers,
+        annotations,
+    ))
+}
+
+pub fn check_extract_metadata<'a>(metadata: &Metadata, segment_reader: &SegmentReader, file_metadata: &'a mut FileMetadata, stream_metadata: &'a mut StreamMetadata) -> Result<(&'a mut FileMetadata, &'a mut StreamMetadata), TdmsParseError> {
+    // check groups
+    if metadata.objects.iter().any(is_valid_group) {
+        return Err(TdmsParseError::UnexpectedGroup);
+    }
+
+    // check channels, string channels of the stream hold annotations
+    if metadata
+        .objects
+        .iter()
+        .any(|x| is_valid_channel(x) && !is_annotation_channel(x, segment_reader.datatype(&x.path)))
+    {
+        return Err(TdmsParseError::UnexpectedChannel);
+    }
+
+    // extract metadata
+    if let Some(root_object) = metadata.objects.iter().find(|x| x.path == "/") {
+        for property in root_object.properties.iter() {
+            let name = property.name.clone();
+            let string_value = property.string_value.clone();
+
+            match name.as_str() {
+                "name" => file_metadata.name = string_value,
+                "LoggerVersionMajor" => {
+                    file_metadata.logger_version_major = parse_property(&name, &string_value)?
+                }
+                "LoggerVersionMinor" => {
+                    file_metadata.logger_version_minor = parse_property(&name, &string_value)?
+                }
+                "LoggerVersionBuild" => {
+                    file_metadata.logger_version_build = parse_property(&name, &string_value)?
+                }
+                "LoggerVersionJSON" => {
+                    file_metadata.logger_version_json = string_value
+                }
+                "TDMSVersionMajor" => {
+                    file_metadata.tdms_version_major = parse_property(&name, &string_value)?
+                }
+                "TDMSVersionMinor" => {
+                    file_metadata.tdms_version_minor = parse_property(&name, &string_value)?
+                }
+                "CarModel" => file_metadata.car_model = string_value,
+                "CarLicensePlate" => file_metadata.car_license_plate = string_value,
+                "ReferenceFileName" => file_metadata.reference_file_name = string_value,
+                "SplitBefore" => file_metadata.reference_split_before = string_value,
+                "SplitAfter" => file_metadata.reference_split_after = string_value,
+                "SplitStartTime" => {
+                    file_metadata.reference_split_start_time = parse_property(&name, &string_value)?
+                }
+                "SplitStopTime" => {
+                    file_metadata.reference_split_stop_time = parse_property(&name, &string_value)?
+                }
+                "FutureTimeEvent" => {
+                    file_metadata.future_time_event = parse_property(&name, &string_value)?
+                }
+                "ZFramePath" => file_metadata.zframe_path = string_value,
+                _ => {
+                    return Err(TdmsParseError::UnexpectedProperty { property: name });
+                }
+            }
+        }
+    }
+
+    if let Some(root_object) = metadata
+        .objects
+        .iter()
+        .find(|x| x.path == "/'Stream'")
+    {
+        for property in root_object.properties.iter() {
+            let name = property.name.clone();
+            let string_value = property.string_value.clone();
+
+            match name.as_str() {
+                "name" => stream_metadata.name = "Stream".to_string(),
+                "ChannelType" => stream_metadata.channel_type = string_value,
+                "ChannelSubType" => {
+                    stream_metadata.channel_sub_type = parse_property(&name, &string_value)?
+                }
+                "ChannelInfo" => stream_metadata.channel_info = string_value,
+                "Sensor" => stream_metadata.sensor = string_value,
+                "PropertiesJSON" => stream_metadata.properties_json = string_value,
+                "Codec" => stream_metadata.codec = string_value,
+                "System" => stream_metadata.system = string_value,
+                "PXIeCard" => stream_metadata.pxie_card = string_value,
+                "SampleTimestamp" => stream_metadata.sample_timestamp = string_value,
+                "SampleType" => stream_metadata.sample_type = string_value,
+                "SampleTypeVersion" => stream_metadata.sample_type_version = string_value,
+                _ => {
+                    return Err(TdmsParseError::UnexpectedProperty { property: name });
+                }
+            }
+        }
+    }
+
+    Ok((file_metadata, stream_metadata))
+}
+
+/// Parses the numeric value of the property `name`.
+fn parse_property<T: FromStr>(name: &str, value: &str) -> Result<T, TdmsParseError> {
+    value.parse().map_err(|_| TdmsParseError::InvalidPropertyValue {
+        property: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn is_root(x: &TdmsObject) -> bool {
+    x.path == "/"
+}
+
+fn is_valid_group(x: &TdmsObject) -> bool {
+    if is_root(x) {
+        return false;
+    }
+    let allowed_groups = ["'stream'"];
+    let group_name = get_group_name(x);
+    if let Some(name) = group_name {
+        !allowed_groups.contains(&name.to_lowercase().as_str())
+    } else {
+        false
+    }
+}
+
+fn get_group_name(object: &TdmsObject) -> Option<String> {
+    let parts = object.path.split('/').collect::<Vec<&str>>();
+    if parts.len() != 2 {
+        None
+    } else {
+        Some(parts[1].to_string())
+    }
+}
+
+fn is_valid_channel(x: &TdmsObject) -> bool {
+    if is_root(x) {
+        return false;
+    }
+    let allowed_channels = ["'data'", "'index'", "'timestamp'", "'header'", "'metadata'"];
+    let channel_name = get_channel_name(x);
+    if let Some(name) = channel_name {
+        !allowed_channels.contains(&name.to_lowercase().as_str())
+    } else {
+        false
+    }
+}
+
+/// Parses the HVDS channels of `path` and returns the index without writing it.
+pub fn index(path: &str) -> Result<Output, TdmsParseError> {
+    index_with(path, StringDecoding::Strict)
+}
+
+/// Like [`index`], decoding the strings of the file with `strings`.
+pub fn index_with(path: &str, strings: StringDecoding) -> Result<Output, TdmsParseError> {
+    let (
+        file_metadata,
+        stream_metadata,
+        data_channel,
+        timestamps,
+        indices,
+        states,
+        frame_numbers,
+        annotations,
+    ) =
+        parse(path, strings)?;
+    build_output(
+        path,
+        "",
+        file_metadata,
+        stream_metadata,
+        data_channel,
+        timestamps,
+        indices,
+        states,
+        frame_numbers,
+        annotations,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_output(
+    path: &str,
+    output_path: &str,
+    file_metadata: FileMetadata,
+    stream_metadata: StreamMetadata,
+    data_channel: DataChannelMap,
+    timestamps: Vec<u64>,
+    indices: Vec<i64>,
+    states: Vec<u32>,
+    frame_numbers: Vec<u32>,
+    annotations: Annotations,
+) -> Result<Output, TdmsParseError> {
+    // process index channel and timestamp channel
+    let count = indices.len() / 2;
+    if count != timestamps.len() / 4 || count != states.len() {
+        return Err(TdmsParseError::ChannelLengthMismatch {
+            index: indices.len(),
+            timestamp: timestamps.len(),
+            header: states.len()
+        });
+    }
+    let mut output_data = Data::with_capacity(count);
+    append_frames(
+        &mut output_data,
+        &data_channel,
+        &timestamps,
+        &indices,
+        &states,
+        &frame_numbers,
+        &annotations,
+        count,
+    );
+
+    let output_stream = Stream {
+        metadata: stream_metadata,
+        data: output_data,
+        diagnostics: None,
+        state_summary: None,
+    };
+
+    let output_file_path = if output_path.is_empty() {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(output_path)
+    };
+    let file_name = output_file_path.file_name().unwrap();
+    let output_file = output::File {
+        raw_file: String::from(file_name.to_str().unwrap()),
+        metadata: file_metadata,
+        stream: output_stream,
+    };
+    Ok(Output {
+        schema: SCHEMA_ID.to_string(),
+        file: output_file,
+    })
+}
+
+/// Appends the first `count` frames of the raw channel values to `data`.
+#[allow(clippy::too_many_arguments)]
+pub fn append_frames(
+    data: &mut Data,
+    data_channel: &DataChannelMap,
+    timestamps: &[u64],
+    indices: &[i64],
+    states: &[u32],
+    frame_numbers: &[u32],
+    annotations: &Annotations,
+    count: usize,
+) {
+    let rows = data.len();
+    for (name, values) in annotations.iter() {
+        if values.len() < count {
+            warn!(
+                "annotation channel {} has {} values for {} frames",
+                name,
+                values.len(),
+                count
+            );
+        }
+        let column = data
+            .annotations
+            .entry(name.clone())
+            .or_insert_with(|| vec![String::new(); rows]);
+        column.extend(values.iter().take(count).cloned());
+    }
+
+    for i in 0..count {
+        let row = data.len();
+        let entry = IndexEntry {
+            start: indices[2 * i],
+            end: indices[2 * i + 1],
+        };
+        trace!("index entry {:?}", entry);
+
+        let range = entry.range().unwrap_or_else(|| {
+            let start = entry.start.unsigned_abs();
+            start..start
+        });
+        let ranges = data_channel.map_range(range.clone());
+        let frame_size: u64 = ranges.iter().map(|range| range.size).sum();
+        let issue = entry.issue().or_else(|| {
+            (frame_size < range.end - range.start).then_some(FrameIssue::PastDataEnd)
+        });
+        if let Some(issue) = issue {
+            warn!(
+                "frame {} has index offsets {}..{} ({:?})",
+                row, entry.start, entry.end, issue
+            );
+            data.invalid_frames.push(InvalidFrame {
+                row,
+                start_offset: entry.start,
+                end_offset: entry.end,
+                issue,
+            });
+        }
+
+        if ranges.len() > 1 {
+            debug!("frame {} spans {} data segments", row, ranges.len());
+            data.fragmented_frames.push(FragmentedFrame {
+                row,
+                ranges: ranges.clone(),
+            });
+        }
+        data.byte_offset
+            .push(ranges.first().map_or(range.start, |range| range.offset));
+        data.frame_size.push(frame_size);
+        let (timestamp, quality) = split_unified_timestamp(timestamps[4 * i]);
+        data.timestamp.push(timestamp);
+        data.timestamp_quality.push(quality);
+        data.state.push(states[i]);
+        data.frame_number.push(frame_numbers[i]);
+    }
+    data.pad_annotations();
+}
+
+/// Appends the values of the annotation channels of a segment.
+pub fn extend_annotations(annotations: &mut Annotations, other: Annotations) {
+    for (name, values) in other {
+        annotations.entry(name).or_default().extend(values);
+    }
+}
+
+/// The relative end timestamps of the first `count` frames of the raw timestamp channel values.
+pub fn end_timestamps(timestamps: &[u64], count: usize) -> Vec<u64> {
+    timestamps[..4 * count]
+        .iter()
+        .skip(1)
+        .step_by(4)
+        .map(|&raw| split_unified_timestamp(raw).0)
+        .collect()
+}
+
+/// Sets `AbsoluteTimestamp` and `AbsoluteTime` of all frames from the relative
+/// timestamps and the file's `FutureTimeEvent`.
+pub fn add_absolute_times(output: &mut Output) {
+    let future_time_event = output.file.metadata.future_time_event;
+    let data = &mut output.file.stream.data;
+    if future_time_event == 0 {
+        warn!("no FutureTimeEvent, cannot determine the absolute times");
+        data.absolute_timestamp.clear();
+        data.absolute_time.clear();
+        return;
+    }
+
+    let absolute_timestamps: Option<Vec<u64>> = data
+        .timestamp
+        .iter()
+        .map(|&timestamp| epoch_nanos(future_time_event, timestamp))
+        .collect();
+    match absolute_timestamps {
+        Some(absolute_timestamps) => data.absolute_timestamp = absolute_timestamps,
+        None => {
+            warn!(
+                "FutureTimeEvent {} puts the frames out of the supported time range",
+                future_time_event
+            );
+            data.absolute_timestamp.clear();
+            data.absolute_time.clear();
+            return;
+        }
+    }
+    data.absolute_time = data
+        .absolute_timestamp
+        .iter()
+        .map(|&nanos| to_iso8601(nanos))
+        .collect();
+}
+
+/// A frame's entry of the Index channel: start and end offset of its payload
+/// relative to the start of the data channel.
+///
+/// Some files hold negative offsets, whose meaning is not documented; such
+/// entries are reported and their absolute values are used to locate the
+/// payload.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexEntry {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl IndexEntry {
+    pub fn has_negative_offset(&self) -> bool {
+        self.start < 0 || self.end < 0
+    }
+
+    /// Byte range within the data channel, `None` if the end lies before the start.
+    pub fn range(&self) -> Option<Range<u64>> {
+        let start = self.start.unsigned_abs();
+        let end = self.end.unsigned_abs();
+        if end >= start {
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+
+    pub fn issue(&self) -> Option<FrameIssue> {
+        if self.range().is_none() {
+            Some(FrameIssue::EndBeforeStart)
+        } else if self.has_negative_offset() {
+            Some(FrameIssue::NegativeOffset)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads a previously written `.hvds.json` index.
+pub fn read_output(json_path: &Path) -> Result<Output, TdmsParseError> {
+    let file = File::open(json_path)?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| TdmsParseError::GeneralError {
+        message: format!("invalid index {}: {}", json_path.display(), e),
+    })
+}
+
+/// Writes the index through a temporary file, an interrupted write leaves the
+/// previous index in place.
+pub fn write_output(output_json_file: &Path, output: &Output) -> Result<(), TdmsParseError> {
+    write_json_atomically(output_json_file, output)
+}
+
+/// A string channel of the stream holding one annotation per frame. `datatype`
+/// is the data type of the channel's last raw data, which a segment without
+/// raw data for the channel does not repeat.
+fn is_annotation_channel(x: &TdmsObject, datatype: Option<&TdmsDataType>) -> bool {
+    let group = x.path.split('/').nth(1).unwrap_or_default();
+    get_channel_name(x).is_some()
+        && group.eq_ignore_ascii_case("'stream'")
+        && datatype == Some(&TdmsDataType::String)
+}
+
+/// Values of the HVDS channels with raw data in one segment.
+#[derive(Debug, Default)]
+pub struct SegmentChannels {
+    /// Location of the Data channel bytes, one entry per chunk.
+    pub data: Vec<DataSegment>,
+    pub timestamps: Vec<u64>,
+    pub indices: Vec<i64>,
+    pub states: Vec<u32>,
+    pub frame_numbers: Vec<u32>,
+    /// Values of the string channels by channel name.
+    pub annotations: Annotations,
+}
+
+/// Reads the HVDS channels of the segment at `segment_pos`, whose header has
+/// been read with the resolved `metadata`.
+pub fn read_segment_channels<T: Read + Seek>(
+    reader: &mut T,
+    segment_pos: u64,
+    file_length: u64,
+    lead_in: &LeadIn,
+    metadata: &Metadata,
+    strings: StringDecoding,
+) -> Result<SegmentChannels, TdmsParseError> {
+    let mut layout = SegmentLayout::new(segment_pos, lead_in, metadata, file_length)?;
+    layout.strings = strings;
+    let mut channels = SegmentChannels::default();
+    for object in metadata.objects.iter() {
+        let channel = match layout.channel(&object.path) {
+            Some(channel) => channel,
+            None => continue,
+        };
+        match get_channel_name(object).map(|name| name.to_lowercase()).as_deref() {
+            Some("'data'") => {
+                channels.data = data_segments(segment_pos, file_length, lead_in, &layout, channel)?
+            }
+            Some("'timestamp'") => {
+                channels.timestamps = read_timestamps(reader, &layout, metadata)?
+            }
+            Some("'index'") => channels.indices = read_indices(reader, &layout, metadata)?,
+            Some("'header'") => {
+                let (states, frame_numbers) = read_headers(reader, &layout, metadata)?;
+                channels.states = states;
+                channels.frame_numbers = frame_numbers;
+            }
+            Some(_) if is_annotation_channel(object, Some(&channel.datatype)) => {
+                let values = layout.read_channel(reader, &object.path)?;
+                if let Some(ChannelData::String(values)) = values {
+                    let name = get_channel_name(object).unwrap_or_default();
+                    channels
+                        .annotations
+                        .insert(name.trim_matches('\'').to_string(), values);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(channels)
+}
+
+/// File ranges of the Data channel bytes in a segment.
+fn data_segments(
+    segment_pos: u64,
+    file_length: u64,
+    lead_in: &LeadIn,
+    layout: &SegmentLayout,
+    channel: &ChannelLayout,
+) -> Result<Vec<DataSegment>, TdmsParseError> {
+    if layout.channels.len() == 1 {
+        // the raw data of a segment that is still being written may not end at a chunk boundary
+        return Ok(vec![DataSegment::new(segment_pos, lead_in, file_length)]);
+    }
+    if layout.interleaved {
+        return Err(TdmsParseError::UnsupportedRawData {
+            path: channel.path.clone(),
+            message: "Data channel interleaved with other channels".to_string(),
+        });
+    }
+    Ok((0..layout.chunk_count)
+        .map(|chunk| DataSegment {
+            absolute_pos: layout.raw_data_pos + chunk * layout.chunk_size + channel.offset,
+            size: channel.size,
+        })
+        .collect())
+}
+
+pub fn read_headers<T: Read + Seek>(
+    reader: &mut T,
+    layout: &SegmentLayout,
+    metadata: &Metadata,
+) -> Result<(Vec<u32>, Vec<u32>), TdmsParseError> {
+    // Frame 1: State
+    // Frame 1: FrameNumber,
+    // Frame 2: State,
+    // Frame 2: FrameNumber
+    match read_channel(reader, layout, metadata, "'header'")? {
+        Some(ChannelData::U32(values)) => {
+            let count = values.len() / 2;
+            let states = values.iter().step_by(2).take(count).copied().collect();
+            let frame_numbers = values.iter().skip(1).step_by(2).take(count).copied().collect();
+            trace!("{:?}", values);
+            Ok((states, frame_numbers))
+        }
+        _ => Err(TdmsParseError::CannotReadHeaderChannel),
+    }
+}
+
+pub fn read_indices<T: Read + Seek>(
+    reader: &mut T,
+    layout: &SegmentLayout,
+    metadata: &Metadata,
+) -> Result<Vec<i64>, TdmsParseError> {
+    // start and end offset of every frame
+    match read_channel(reader, layout, metadata, "'index'")? {
+        Some(ChannelData::I64(mut indices)) => {
+            indices.truncate(indices.len() / 2 * 2);
+            trace!("{:?}", indices);
+            Ok(indices)
+        }
+        _ => Err(TdmsParseError::CannotReadIndexChannel),
+    }
+}
+
+pub fn read_timestamps<T: Read + Seek>(
+    reader: &mut T,
+    layout: &SegmentLayout,
+    metadata: &Metadata,
+) -> Result<Vec<u64>, TdmsParseError> {
+    // In the Timestamp channel, each frame has 4 u64 timestamps.
+    // Relative Start Timestamp -> Unified hardware timestamps
+    // Relative End Timestamp
+    // Absolute Start Timestamp -> Unified hardware timestamps
+    // Absolute End Timestamp
+    // The values keep the quality bits, see `split_unified_timestamp`.
+    match read_channel(reader, layout, metadata, "'timestamp'")? {
+        Some(ChannelData::U64(mut timestamps)) => {
+            timestamps.truncate(timestamps.len() / 4 * 4);
+            if log_enabled!(log::Level::Trace) {
+                for frame in timestamps.chunks_exact(4) {
+                    let frame: Vec<_> =
+                        frame.iter().map(|&t| split_unified_timestamp(t)).collect();
+                    trace!("{:?}", frame);
+                }
+            }
+            Ok(timestamps)
+        }
+        _ => Err(TdmsParseError::CannotReadTimestampChannel),
+    }
+}
+
+/// Decodes the values of the channel `name` in the segment, `None` if it has no raw data there.
+fn read_channel<T: Read + Seek>(
+    reader: &mut T,
+    layout: &SegmentLayout,
+    metadata: &Metadata,
+    name: &str,
+) -> Result<Option<ChannelData>, TdmsParseError> {
+    match find_channel(metadata, name) {
+        Some(object) => {
+            debug!("reading {} {:?}", object.path, object.raw_data_index);
+            layout.read_channel(reader, &object.path)
+        }
+        None => Ok(None),
+    }
+}
+
+/// The object of the channel `name` (quoted, e.g. `'index'`) in the segment's object list.
+fn find_channel<'a>(metadata: &'a Metadata, name: &str) -> Option<&'a TdmsObject> {
+    metadata.objects.iter().find(|x| {
+        get_channel_name(x).is_some_and(|channel| channel.to_lowercase() == name)
+    })
+}
+
+pub fn get_channel_name(object: &TdmsObject) -> Option<String> {
+    let parts = object.path.split('/').collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        None
+    } else {
+        Some(parts[2].to_string())
+    }
+}
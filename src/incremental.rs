@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_channel::DataChannelMap;
+use crate::diagnostics::Diagnostics;
+use crate::hvds::{
+    add_absolute_times, append_frames, check_extract_metadata, end_timestamps, extend_annotations,
+    read_output, read_segment_channels, write_output, IndexOptions,
+};
+use crate::output::{
+    self, Annotations, Data, FileMetadata, Output, Stream, StreamMetadata, SCHEMA_ID,
+};
+use crate::tdms_lead_in::{has_lead_in_at, LeadIn};
+use crate::tdms_metadata::Metadata;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::SegmentReader;
+use crate::util::{write_json_atomically, StringDecoding};
+
+/// Progress of the incremental indexing of a file that is still being written.
+///
+/// Stored next to the index as `<file>.hvds.state.json`. Channel values that do
+/// not form a complete frame yet (e.g. timestamps whose index segment has not
+/// been written) are kept until the next run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IncrementalState {
+    #[serde(rename = "NextSegmentOffset")]
+    pub next_segment_pos: u64,
+
+    #[serde(rename = "DataSegments")]
+    pub data_channel: DataChannelMap,
+
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+
+    #[serde(rename = "PendingTimestamps")]
+    pub timestamps: Vec<u64>,
+
+    #[serde(rename = "PendingIndices")]
+    pub indices: Vec<i64>,
+
+    #[serde(rename = "PendingStates")]
+    pub states: Vec<u32>,
+
+    #[serde(rename = "PendingFrameNumbers")]
+    pub frame_numbers: Vec<u32>,
+
+    /// Annotation values not assigned to a frame yet.
+    #[serde(rename = "PendingAnnotations", default)]
+    pub annotations: Annotations,
+
+    /// Number of frames per annotation channel that have their value, the
+    /// following frames hold an empty string until the value is written.
+    #[serde(rename = "AnnotationCounts", default)]
+    pub annotation_counts: BTreeMap<String, usize>,
+}
+
+/// Indexes the segments appended to `path` since the previous run and appends
+/// their frames to the existing `.hvds.json`. Returns the updated index and the
+/// number of new frames.
+///
+/// Segments that are still being written are left for the next run. The
+/// diagnostics, if requested, cover all frames indexed so far; only the end
+/// timestamps of earlier runs are taken from the previous diagnostics.
+pub fn index_incremental(
+    path: &Path,
+    options: &IndexOptions,
+) -> Result<(Output, usize), TdmsParseError> {
+    let json_path = path.with_extension("hvds.json");
+    let state_path = path.with_extension("hvds.state.json");
+
+    let file = File::open(path)?;
+    let file_length = file.metadata()?.len();
+
+    let resumed = match load_state(&state_path)? {
+        Some(state) if json_path.exists() && state.next_segment_pos <= file_length => {
+            resume(&json_path, state)
+        }
+        _ => None,
+    };
+    let (mut output, mut state) = resumed.unwrap_or_else(|| {
+        (
+            new_output(path, &options.output_path),
+            IncrementalState::default(),
+        )
+    });
+    debug!("continuing at segment offset {}", state.next_segment_pos);
+
+    let mut reader = BufReader::new(&file);
+    let mut segment_reader =
+        replay_headers(&mut reader, state.next_segment_pos, options.string_decoding)?;
+    let mut pos = state.next_segment_pos;
+    while has_lead_in_at(pos, file_length) {
+        reader.seek(SeekFrom::Start(pos))?;
+        let lead_in = LeadIn::read(&mut reader)?;
+        let next_pos = match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) if next_pos <= file_length => next_pos,
+            _ => {
+                debug!("segment at {} is still being written", pos);
+                break;
+            }
+        };
+
+        reader.seek(SeekFrom::Start(pos))?;
+        let (lead_in, metadata) = segment_reader.read_header(&mut reader)?;
+        let file_version = &mut output.file.metadata.tdms_file_version;
+        *file_version = (*file_version).max(lead_in.version);
+        check_extract_metadata(
+            &metadata,
+            &segment_reader,
+            &mut output.file.metadata,
+            &mut output.file.stream.metadata,
+        )?;
+        read_channel_segment(
+            &mut reader,
+            pos,
+            file_length,
+            &lead_in,
+            &metadata,
+            options.string_decoding,
+            &mut state,
+        )?;
+
+        pos = next_pos;
+        state.next_segment_pos = pos;
+    }
+
+    let count = if state.data_channel.is_empty() {
+        0
+    } else {
+        (state.timestamps.len() / 4)
+            .min(state.indices.len() / 2)
+            .min(state.states.len())
+    };
+    let end_timestamps = end_timestamps(&state.timestamps, count);
+    append_frames(
+        &mut output.file.stream.data,
+        &state.data_channel,
+        &state.timestamps,
+        &state.indices,
+        &state.states,
+        &state.frame_numbers,
+        &Annotations::new(),
+        count,
+    );
+    state.timestamps.drain(..4 * count);
+    state.indices.drain(..2 * count);
+    state.states.drain(..count);
+    state.frame_numbers.drain(..count);
+    state.frame_count += count;
+    assign_annotations(&mut output.file.stream.data, &mut state);
+
+    let previous = output.file.stream.diagnostics.take();
+    if options.diagnostics {
+        let mut diagnostics = Diagnostics::analyze(
+            &output.file.metadata,
+            &output.file.stream.data,
+            &end_timestamps,
+        );
+        if let Some(previous) = previous {
+            diagnostics
+                .timestamps
+                .carry_over(&previous.timestamps, state.frame_count - count);
+        }
+        output.file.stream.diagnostics = Some(diagnostics);
+    }
+    if let Some(decoder) = &options.state_decoder {
+        let stream = &mut output.file.stream;
+        stream.state_summary = decoder.summarize(&stream.metadata, &stream.data.state);
+    }
+    if options.absolute_time {
+        add_absolute_times(&mut output);
+    } else {
+        output.file.stream.data.absolute_timestamp.clear();
+        output.file.stream.data.absolute_time.clear();
+    }
+
+    write_output(&json_path, &output)?;
+    write_json_atomically(&state_path, &state)?;
+    Ok((output, count))
+}
+
+/// Fills the annotation values read so far into the frames, including frames of
+/// earlier runs that were padded with an empty string like in a full index.
+fn assign_annotations(data: &mut Data, state: &mut IncrementalState) {
+    let rows = data.len();
+    for (name, values) in state.annotations.iter_mut() {
+        let filled = state.annotation_counts.entry(name.clone()).or_insert(0);
+        let column = data
+            .annotations
+            .entry(name.clone())
+            .or_insert_with(|| vec![String::new(); rows]);
+        let count = values.len().min(rows.saturating_sub(*filled));
+        for (row, value) in (*filled..).zip(values.drain(..count)) {
+            column[row] = value;
+        }
+        *filled += count;
+    }
+}
+
+/// The index of the earlier runs, `None` if it has to be rebuilt from the start
+/// of the file.
+fn resume(json_path: &Path, state: IncrementalState) -> Option<(Output, IncrementalState)> {
+    let mut output = match read_output(json_path) {
+        Ok(output) => output,
+        Err(e) => {
+            warn!("{}, reindexing", e);
+            return None;
+        }
+    };
+    // frames written by a run that was interrupted before saving its state
+    output.file.stream.data.truncate(state.frame_count);
+    let data = &output.file.stream.data;
+    let has_annotation_counts = data.annotations.len() == state.annotation_counts.len();
+    if data.timestamp_quality.len() != state.frame_count || !has_annotation_counts {
+        warn!(
+            "{} was written by an older version, reindexing",
+            json_path.display()
+        );
+        return None;
+    }
+    Some((output, state))
+}
+
+/// Rebuilds the object state of the segments before `until` that were indexed by earlier runs.
+fn replay_headers(
+    reader: &mut BufReader<&File>,
+    until: u64,
+    strings: StringDecoding,
+) -> Result<SegmentReader, TdmsParseError> {
+    let mut segment_reader = SegmentReader::with_string_decoding(strings);
+    let mut pos = 0;
+    while pos < until {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (lead_in, _) = segment_reader.read_header(reader)?;
+        pos = match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) => next_pos,
+            None => break,
+        };
+    }
+    Ok(segment_reader)
+}
+
+fn read_channel_segment(
+    reader: &mut BufReader<&File>,
+    segment_pos: u64,
+    file_length: u64,
+    lead_in: &LeadIn,
+    metadata: &Metadata,
+    strings: StringDecoding,
+    state: &mut IncrementalState,
+) -> Result<(), TdmsParseError> {
+    let channels =
+        read_segment_channels(reader, segment_pos, file_length, lead_in, metadata, strings)?;
+    for segment in channels.data {
+        state.data_channel.push(segment);
+    }
+    state.timestamps.extend(channels.timestamps);
+    state.indices.extend(channels.indices);
+    state.states.extend(channels.states);
+    state.frame_numbers.extend(channels.frame_numbers);
+    extend_annotations(&mut state.annotations, channels.annotations);
+    Ok(())
+}
+
+/// An empty index, `output_path` replaces `path` for the `RawFile` name like in
+/// [`crate::hvds::index_file`].
+fn new_output(path: &Path, output_path: &str) -> Output {
+    let raw_file_path = if output_path.is_empty() {
+        path
+    } else {
+        Path::new(output_path)
+    };
+    let raw_file = raw_file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Output {
+        schema: SCHEMA_ID.to_string(),
+        file: output::File {
+            raw_file,
+            metadata: FileMetadata::new(),
+            stream: Stream {
+                metadata: StreamMetadata::new(),
+                data: Data::default(),
+                diagnostics: None,
+                state_summary: None,
+            },
+        },
+    }
+}
+
+fn load_state(state_path: &Path) -> Result<Option<IncrementalState>, TdmsParseError> {
+    if !state_path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(state_path)?;
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => {
+            warn!("ignoring invalid state {}: {}", state_path.display(), e);
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{fs::OpenOptions, io::BufReader};
+
+use serde::Serialize;
+
+use crate::tdms_object::{RawDataIndex, TdmsObject};
+use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::{read_segments_with, TdmsSegment};
+use crate::util::StringDecoding;
+
+#[derive(Debug, Serialize)]
+pub struct SegmentReport {
+    #[serde(rename(serialize = "Index"))]
+    pub index: usize,
+
+    #[serde(rename(serialize = "AbsoluteOffset"))]
+    pub absolute_offset: u64,
+
+    #[serde(rename(serialize = "Version"))]
+    pub version: u32,
+
+    #[serde(rename(serialize = "FormatVersion"))]
+    pub format_version: &'static str,
+
+    #[serde(rename(serialize = "TocMask"))]
+    pub toc_mask: u32,
+
+    #[serde(rename(serialize = "ContainsMetadata"))]
+    pub contains_metadata: bool,
+
+    #[serde(rename(serialize = "ContainsNewObjectList"))]
+    pub contains_new_object_list: bool,
+
+    #[serde(rename(serialize = "ContainsRawData"))]
+    pub contains_raw_data: bool,
+
+    #[serde(rename(serialize = "IsInterleaved"))]
+    pub is_interleaved: bool,
+
+    #[serde(rename(serialize = "IsBigEndian"))]
+    pub is_big_endian: bool,
+
+    #[serde(rename(serialize = "ContainsDaqmxRawData"))]
+    pub contains_daqmx_raw_data: bool,
+
+    #[serde(rename(serialize = "NextSegmentOffset"))]
+    pub next_segment_offset: u64,
+
+    #[serde(rename(serialize = "RawDataOffset"))]
+    pub raw_data_offset: u64,
+
+    #[serde(rename(serialize = "Objects"))]
+    pub objects: Vec<ObjectReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObjectReport {
+    #[serde(rename(serialize = "Path"))]
+    pub path: String,
+
+    #[serde(rename(serialize = "RawDataIndex"))]
+    pub raw_data_index: String,
+
+    #[serde(rename(serialize = "DataType"))]
+    pub datatype: Option<String>,
+
+    #[serde(rename(serialize = "ArrayDimension"))]
+    pub array_dimension: Option<u32>,
+
+    #[serde(rename(serialize = "NumberOfValues"))]
+    pub number_of_values: Option<u64>,
+
+    #[serde(rename(serialize = "TotalSizeBytes"))]
+    pub total_size_bytes: Option<u64>,
+
+    #[serde(rename(serialize = "Properties"))]
+    pub properties: u32,
+}
+
+impl SegmentReport {
+    fn new(index: usize, segment: &TdmsSegment) -> Self {
+        let lead_in = &segment.lead_in;
+        let objects = match &segment.metadata {
+            Some(metadata) => metadata.objects.iter().map(ObjectReport::new).collect(),
+            None => vec![],
+        };
+
+        SegmentReport {
+            index,
+            absolute_offset: segment.absolute_pos,
+            version: lead_in.version,
+            format_version: lead_in.format_version(),
+            toc_mask: lead_in.toc_mask,
+            contains_metadata: lead_in.contains_metadata,
+            contains_new_object_list: lead_in.contains_new_object_list,
+            contains_raw_data: lead_in.contains_raw_data,
+            is_interleaved: lead_in.is_raw_data_in_segment_interleaved,
+            is_big_endian: lead_in.is_toc_big_endian,
+            contains_daqmx_raw_data: lead_in.contains_daqmx_raw_data,
+            next_segment_offset: lead_in.next_segment_offset,
+            raw_data_offset: lead_in.raw_data_offset,
+            objects,
+        }
+    }
+}
+
+impl ObjectReport {
+    fn new(object: &TdmsObject) -> Self {
+        let mut report = ObjectReport {
+            path: object.path.clone(),
+            raw_data_index: String::new(),
+            datatype: None,
+            array_dimension: None,
+            number_of_values: None,
+            total_size_bytes: None,
+            properties: object.property_size,
+        };
+
+        match &object.raw_data_index {
+            RawDataIndex::NoRawData => report.raw_data_index = "NoRawData".to_string(),
+            RawDataIndex::SameAsPrevious => report.raw_data_index = "SameAsPrevious".to_string(),
+            RawDataIndex::Daqmx(daqmx) => {
+                report.raw_data_index = "Daqmx".to_string();
+                report.datatype = Some(format!("{:?}", daqmx.datatype));
+                report.array_dimension = Some(daqmx.array_dimension);
+                report.number_of_values = Some(daqmx.chunk_size);
+            }
+            RawDataIndex::DaqmxDigitalLine(daqmx) => {
+                report.raw_data_index = "DaqmxDigitalLine".to_string();
+                report.datatype = Some(format!("{:?}", daqmx.datatype));
+                report.array_dimension = Some(daqmx.array_dimension);
+                report.number_of_values = Some(daqmx.chunk_size);
+            }
+            RawDataIndex::NewDataIndex(index) => {
+                report.raw_data_index = "NewDataIndex".to_string();
+                report.datatype = Some(format!("{:?}", index.datatype));
+                report.array_dimension = Some(index.array_dimension);
+                report.number_of_values = Some(index.chunk_size);
+                report.total_size_bytes = Some(index.total_size_bytes);
+            }
+        }
+
+        report
+    }
+}
+
+/// Reads the segment table of a TDMS file without interpreting the HVDS channels.
+/// Strings are decoded lossily, so files with invalid UTF-8 can still be inspected.
+pub fn inspect(path: &str) -> Result<Vec<SegmentReport>, TdmsParseError> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let mut reader = BufReader::new(file);
+
+    let segments = read_segments_with(&mut reader, StringDecoding::Lossy)?;
+    Ok(segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| SegmentReport::new(index, segment))
+        .collect())
+}
+
+pub fn print_table(reports: &[SegmentReport]) {
+    let mut versions: Vec<&str> = reports.iter().map(|r| r.format_version).collect();
+    versions.sort_unstable();
+    versions.dedup();
+    if !versions.is_empty() {
+        println!("TDMS file format version {}", versions.join(", "));
+        println!();
+    }
+    println!(
+        "{:>5}  {:>12}  {:>7}  {:>10}  {:<6}  {:>16}  {:>16}",
+        "#", "Offset", "Version", "ToC", "Flags", "NextSegOffset", "RawDataOffset"
+    );
+    for report in reports {
+        println!(
+            "{:>5}  {:>12}  {:>7}  {:#010x}  {:<6}  {:>16}  {:>16}",
+            report.index,
+            report.absolute_offset,
+            report.version,
+            report.toc_mask,
+            flags(report),
+            report.next_segment_offset,
+            report.raw_data_offset
+        );
+        for object in report.objects.iter() {
+            println!(
+                "{:>7}{:<40} {:<15} {:<14} {:>12} {:>3} props",
+                "",
+                object.path,
+                object.raw_data_index,
+                object.datatype.as_deref().unwrap_or("-"),
+                object
+                    .number_of_values
+                    .map_or("-".to_string(), |n| n.to_string()),
+                object.properties
+            );
+        }
+    }
+    println!();
+    println!(
+        "Flags: M metadata, N new object list, R raw data, I interleaved, B big-endian, D DAQmx"
+    );
+}
+
+fn flags(report: &SegmentReport) -> String {
+    [
+        (report.contains_metadata, 'M'),
+        (report.contains_new_object_list, 'N'),
+        (report.contains_raw_data, 'R'),
+        (report.is_interleaved, 'I'),
+        (report.is_big_endian, 'B'),
+        (report.contains_daqmx_raw_data, 'D'),
+    ]
+    .iter()
+    .map(|&(set, flag)| if set { flag } else { '-' })
+    .collect()
+}
@@ -0,0 +1,26 @@
+#[macro_use]
+extern crate log;
+
+pub mod batch;
+pub mod chain;
+pub mod channel_decoder;
+pub mod data_channel;
+pub mod diagnostics;
+pub mod extract;
+pub mod frame_reader;
+pub mod hvds;
+pub mod incremental;
+pub mod inspect;
+pub mod logging;
+pub mod merged_index;
+pub mod output;
+pub mod state_decoder;
+pub mod tdms_datatype;
+pub mod tdms_lead_in;
+pub mod tdms_metadata;
+pub mod tdms_object;
+pub mod tdms_parse_error;
+pub mod tdms_segment;
+pub mod unified_time;
+pub mod util;
+pub mod watch;
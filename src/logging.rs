@@ -0,0 +1,69 @@
+use log::LevelFilter;
+use log4rs::append::console::{ConsoleAppender, Target};
+use log4rs::append::file::FileAppender;
+use log4rs::append::Append;
+use log4rs::config::{Appender, Config, Root};
+use log4rs::encode::json::JsonEncoder;
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::Encode;
+
+use crate::tdms_parse_error::TdmsParseError;
+
+/// Name of the only appender of the logging configuration.
+pub const LOG_APPENDER: &str = "log";
+
+/// Builds the logging configuration, `None` if nothing is logged.
+///
+/// `destination` is `stderr`, `none` or the path of a log file, which is
+/// created or appended to.
+pub fn log_config(
+    level: LevelFilter,
+    destination: &str,
+    json_format: bool,
+) -> Result<Option<Config>, TdmsParseError> {
+    if destination == "none" || level == LevelFilter::Off {
+        return Ok(None);
+    }
+
+    let encoder = || -> Box<dyn Encode> {
+        if json_format {
+            Box::new(JsonEncoder::new())
+        } else {
+            Box::new(PatternEncoder::default())
+        }
+    };
+    let appender: Box<dyn Append> = if destination == "stderr" {
+        Box::new(
+            ConsoleAppender::builder()
+                .target(Target::Stderr)
+                .encoder(encoder())
+                .build(),
+        )
+    } else {
+        Box::new(
+            FileAppender::builder()
+                .encoder(encoder())
+                .build(destination)?,
+        )
+    };
+
+    let config = Config::builder()
+        .appender(Appender::builder().build(LOG_APPENDER, appender))
+        .build(Root::builder().appender(LOG_APPENDER).build(level))
+        .map_err(|e| TdmsParseError::GeneralError {
+            message: e.to_string(),
+        })?;
+    Ok(Some(config))
+}
+
+/// Sets up the global logger once for the whole process.
+pub fn init_log(
+    level: LevelFilter,
+    destination: &str,
+    json_format: bool,
+) -> Result<(), TdmsParseError> {
+    if let Some(config) = log_config(level, destination, json_format)? {
+        log4rs::init_config(config)?;
+    }
+    Ok(())
+}
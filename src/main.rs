@@ -1,38 +1,21 @@
 #[macro_use]
 extern crate log;
-extern crate log4rs;
-extern crate serde;
-extern crate serde_json;
 
-use fs::File;
-use std::{fs, fs::OpenOptions, io::BufReader, io::Seek, io::SeekFrom, path::PathBuf};
+use std::{fmt::Display, path::Path, path::PathBuf, process, str::FromStr, thread, time::Duration};
 
-use chrono::{TimeZone, Utc};
-use clap::{App, Arg};
-use log::{LevelFilter, SetLoggerError};
-use log4rs::{
-    append::console::ConsoleAppender, append::console::Target, append::file::FileAppender,
-    config::Appender, config::Config, config::Root, Handle,
-};
-use serde_json::to_writer_pretty;
+use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::{json, to_string_pretty};
 
-use output::{Data, FileMetadata, Output, Stream, StreamMetadata};
-use tdms_lead_in::LeadIn;
-use tdms_metadata::Metadata;
-use tdms_object::RawDataIndex;
-use tdms_parse_error::TdmsParseError;
-use util::{read_i64, read_unified_timestamp};
-
-use crate::tdms_object::TdmsObject;
-use crate::util::read_u32;
-
-mod output;
-mod tdms_datatype;
-mod tdms_lead_in;
-mod tdms_metadata;
-mod tdms_object;
-mod tdms_parse_error;
-mod util;
+use adas_hvds::extract::{extract_with, ExtractTarget, FrameSelection};
+use adas_hvds::hvds::{index_file, IndexOptions};
+use adas_hvds::incremental::index_incremental;
+use adas_hvds::logging::init_log;
+use adas_hvds::merged_index::{MergedFormat, MergedIndex};
+use adas_hvds::state_decoder::StateDecoder;
+use adas_hvds::tdms_parse_error::{TdmsParseError, EXIT_SUCCESS};
+use adas_hvds::util::StringDecoding;
+use adas_hvds::watch::{WatchOptions, Watcher};
+use adas_hvds::{batch, chain, inspect};
 
 fn main() {
     let matches = App::new("ADAS HVDS Indexer")
@@ -41,498 +24,463 @@ fn main() {
         .about("Generate index for HVDS in a TDMS file with JSON format")
         .arg(
             Arg::with_name("INPUT")
-                .help("Input file path")
+                .help("Input file paths, directories (searched recursively) or glob patterns")
                 .required(true)
+                .multiple(true)
                 .index(1),
         )
         .arg(Arg::with_name("output").short("o").help("output file path"))
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .help("number of files indexed in parallel (defaults to the number of CPUs)"),
+        )
+        .arg(
+            Arg::with_name("incremental")
+                .long("incremental")
+                .help("only index the segments appended since the previous run of a file still being written"),
+        )
+        .arg(
+            Arg::with_name("diagnostics")
+                .long("diagnostics")
+                .help("check the frame numbers and add a Diagnostics section to the index"),
+        )
+        .arg(
+            Arg::with_name("absolute-time")
+                .long("absolute-time")
+                .help("add the absolute UTC time of every frame based on the FutureTimeEvent"),
+        )
+        .arg(
+            Arg::with_name("lossy-strings")
+                .long("lossy-strings")
+                .global(true)
+                .help("replace invalid UTF-8 in names and properties instead of failing the file"),
+        )
+        .arg(
+            Arg::with_name("state-definitions")
+                .long("state-definitions")
+                .takes_value(true)
+                .env("ADAS_HVDS_STATE_DEFINITIONS")
+                .help("JSON file defining the State bits per SampleType, adds a StateSummary section to the index"),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .takes_value(true)
+                .env("ADAS_HVDS_LOG_LEVEL")
+                .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+                .default_value("info")
+                .global(true)
+                .help("log level"),
+        )
+        .arg(
+            Arg::with_name("log")
+                .long("log")
+                .takes_value(true)
+                .env("ADAS_HVDS_LOG")
+                .default_value("stderr")
+                .global(true)
+                .help("log destination: stderr, none or a log file path"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .takes_value(true)
+                .env("ADAS_HVDS_LOG_FORMAT")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .global(true)
+                .help("log line format"),
+        )
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Print the raw TDMS segment table")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Input file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["table", "json"])
+                        .default_value("table")
+                        .help("output format"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Index TDMS files in the given directories once the logger has closed them")
+                .arg(
+                    Arg::with_name("DIR")
+                        .help("Directories to watch (recursively)")
+                        .required(true)
+                        .multiple(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .takes_value(true)
+                        .help("state file listing the indexed files [default: <first DIR>/.hvds-watch-state.json]"),
+                )
+                .arg(
+                    Arg::with_name("settle")
+                        .long("settle")
+                        .takes_value(true)
+                        .default_value("10")
+                        .help("seconds a file has to stay unchanged before it is indexed"),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("2")
+                        .help("seconds between two directory scans"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("chain")
+                .about("Follow the split links of a recording and write a recording-level index")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Any split of the recording")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("recording index [default: <first split>.recording.json]"),
+                )
+                .arg(
+                    Arg::with_name("merged")
+                        .long("merged")
+                        .takes_value(true)
+                        .help("also write a merged index of the frames of all splits"),
+                )
+                .arg(
+                    Arg::with_name("merged-format")
+                        .long("merged-format")
+                        .takes_value(true)
+                        .possible_values(&["json", "binary"])
+                        .default_value("json")
+                        .help("format of the merged index"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("extract")
+                .about("Write the raw payload of selected frames")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Input file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("index")
+                        .long("index")
+                        .takes_value(true)
+                        .help("existing .hvds.json index of the input file, parsed from the file if omitted"),
+                )
+                .arg(
+                    Arg::with_name("first-frame")
+                        .long("first-frame")
+                        .takes_value(true)
+                        .help("first FrameNumber to extract"),
+                )
+                .arg(
+                    Arg::with_name("last-frame")
+                        .long("last-frame")
+                        .takes_value(true)
+                        .help("last FrameNumber to extract"),
+                )
+                .arg(
+                    Arg::with_name("start-time")
+                        .long("start-time")
+                        .takes_value(true)
+                        .help("first relative start timestamp to extract"),
+                )
+                .arg(
+                    Arg::with_name("end-time")
+                        .long("end-time")
+                        .takes_value(true)
+                        .help("last relative start timestamp to extract"),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .long("state")
+                        .takes_value(true)
+                        .help("only extract frames with this State"),
+                )
+                .arg(
+                    Arg::with_name("out-dir")
+                        .long("out-dir")
+                        .takes_value(true)
+                        .required_unless("output")
+                        .conflicts_with("output")
+                        .help("directory receiving one file per frame"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("file receiving all frames, each preceded by a framing header"),
+                ),
+        )
         .get_matches();
-    debug!("{:?}", matches);
 
-    let path = matches.value_of("INPUT").unwrap();
-    let output_path = matches.value_of("output").unwrap_or("");
-
-    let result = parse(path);
-    if result.is_err() {
-        error!("{:?}", result.err().unwrap());
-        return;
+    let level = matches.value_of("log-level").unwrap().parse().unwrap();
+    let destination = matches.value_of("log").unwrap();
+    let json_format = matches.value_of("log-format") == Some("json");
+    if let Err(e) = init_log(level, destination, json_format) {
+        report_error("", &e);
+        process::exit(e.category().exit_code());
     }
+    debug!("{:?}", matches);
+    let string_decoding = if matches.is_present("lossy-strings") {
+        StringDecoding::Lossy
+    } else {
+        StringDecoding::Strict
+    };
 
-    let (file_metadata, stream_metadata, data_start_pos, timestamps, indices,states, frame_numbers) = result.ok().unwrap();
-
-    info!("Preparing to output");
-    let result = output(
-        path,
-        output_path,
-        file_metadata,
-        stream_metadata,
-        data_start_pos,
-        timestamps,
-        indices,
-        states,
-        frame_numbers
-    );
-    if result.is_err() {
-        error!("{:?}", result.err().unwrap());
-    }
-}
-
-fn parse(path: &str) -> Result<(FileMetadata, StreamMetadata, u64, Vec<u64>, Vec<i64>, Vec<u32>, Vec<u32>), TdmsParseError> {
-    let _ = init_log(String::from(path))?;
-    let mut file_metadata = FileMetadata::new();
-    let mut stream_metadata = StreamMetadata::new();
-
-    let file = Some(OpenOptions::new().read(true).open(path)?);
-    let mut reader = BufReader::new(file.as_ref().unwrap());
-
-    /*
-        read the first segment to obtain the data starting pos
-    */
-    let (lead_in, metadata) = read_segment_header(&mut reader)?;
-    check_extract_metadata(&metadata, &mut file_metadata, &mut stream_metadata)?;
-    let data_start_pos = lead_in.raw_data_offset + 28;
-
-    // skip to the next segment starting point
-    let _ = reader.seek(SeekFrom::Start(lead_in.next_segment_offset + 28));
-
-    /*
-        read the next segment (data segment cont.)
-    */
-    let mut current_pos: u64 = get_current_read_pos(&mut reader)?;
-    debug!("data channel byte offset = {:?}", current_pos);
-    let (lead_in, metadata) = read_segment_header(&mut reader)?;
-    check_extract_metadata(&metadata, &mut file_metadata, &mut stream_metadata)?;
-    // let data_start_pos_2 = get_current_read_pos(&mut reader)?;
-
-    // skip to the next segment starting point
-    let _ = reader.seek(SeekFrom::Start(
-        current_pos + lead_in.next_segment_offset + 28,
-    ));
-
-    /*
-        read the next segment to get the information for Timestamps
-    */
-    current_pos = get_current_read_pos(&mut reader)?;
-    debug!("timestamp channel byte offset = {:?}", current_pos);
-    let (lead_in, metadata) = read_segment_header(&mut reader)?;
-    check_extract_metadata(&metadata, &mut file_metadata, &mut stream_metadata)?;
-    let timestamps = read_timestamps(&mut reader, &lead_in, &metadata)?;
-
-    // skip to the next segment starting point
-    let _ = reader.seek(SeekFrom::Start(
-        current_pos + lead_in.next_segment_offset + 28,
-    ));
-
-    /*
-       read the next segment to get the information for index
-    */
-    current_pos = get_current_read_pos(&mut reader)?;
-    debug!("index channel byte offset = {:?}", current_pos);
-    let (lead_in, metadata) = read_segment_header(&mut reader)?;
-    check_extract_metadata(&metadata, &mut file_metadata, &mut stream_metadata)?;
-    let indices = read_indices(&mut reader, &lead_in, &metadata)?;
-
-    // skip to the next segment starting point
-    let _ = reader.seek(SeekFrom::Start(
-        current_pos + lead_in.next_segment_offset + 28,
-    ));
-
-    /*
-        read the next segment to get the information for header channel
-    */
-    current_pos = get_current_read_pos(&mut reader)?;
-    debug!("header channel byte offset = {:?}", current_pos);
-    let (lead_in, metadata) = read_segment_header(&mut reader)?;
-    check_extract_metadata(&metadata, &mut file_metadata, &mut stream_metadata)?;
-    let (states, frame_numbers) = read_headers(&mut reader, &lead_in, &metadata)?;
-
-    // skip to the next segment starting point
-    let _ = reader.seek(SeekFrom::Start(
-        current_pos + lead_in.next_segment_offset + 28,
-    ));
-
-    // // check whether we have more segments?
-    // if lead_in.next_segment_offset != 0xffff_ffff_ffff_ffff {
-    //     return Err(TdmsParseError::UnexpectedSegment);
-    // }
-
-    Ok((file_metadata, stream_metadata, data_start_pos, timestamps, indices, states, frame_numbers))
-}
-
-fn check_extract_metadata<'a>(metadata: &Metadata, file_metadata: &'a mut FileMetadata, stream_metadata: &'a mut StreamMetadata) -> Result<(&'a mut FileMetadata, &'a mut StreamMetadata), TdmsParseError> {
-    // check groups
-    if metadata.objects.iter().any(|x| is_valid_group(x)) {
-        return Err(TdmsParseError::UnexpectedGroup);
-    }
-
-    // check channels
-    if metadata.objects.iter().any(|x| is_valid_channel(x)) {
-        return Err(TdmsParseError::UnexpectedChannel);
-    }
-
-    // extract metadata
-    if let Some(root_object) = metadata.objects.iter().find(|x| x.path == "/") {
-        for property in root_object.properties.iter() {
-            let name = property.name.clone();
-            let string_value = property.string_value.clone();
-
-            match name.as_str() {
-                "name" => file_metadata.name = string_value,
-                "LoggerVersionMajor" => {
-                    file_metadata.logger_version_major = string_value.parse().unwrap()
-                }
-                "LoggerVersionMinor" => {
-                    file_metadata.logger_version_minor = string_value.parse().unwrap()
-                }
-                "LoggerVersionBuild" => {
-                    file_metadata.logger_version_build = string_value.parse().unwrap()
-                }
-                "LoggerVersionJSON" => {
-                    file_metadata.logger_version_json = string_value.parse().unwrap()
-                }
-                "TDMSVersionMajor" => {
-                    file_metadata.tdms_version_major = string_value.parse().unwrap()
-                }
-                "TDMSVersionMinor" => {
-                    file_metadata.tdms_version_minor = string_value.parse().unwrap()
-                }
-                "CarModel" => file_metadata.car_model = string_value,
-                "CarLicensePlate" => file_metadata.car_license_plate = string_value,
-                "ReferenceFileName" => file_metadata.reference_file_name = string_value,
-                "SplitBefore" => file_metadata.reference_split_before = string_value,
-                "SplitAfter" => file_metadata.reference_split_after = string_value,
-                "SplitStartTime" => {
-                    file_metadata.reference_split_start_time = string_value.parse().unwrap()
-                }
-                "SplitStopTime" => {
-                    file_metadata.reference_split_stop_time = string_value.parse().unwrap()
-                }
-                "FutureTimeEvent" => {
-                    file_metadata.future_time_event = string_value.parse().unwrap()
-                }
-                "ZFramePath" => file_metadata.zframe_path = string_value,
-                _ => {
-                    return Err(TdmsParseError::UnexpectedProperty { property: name });
+    if let Some(inspect_matches) = matches.subcommand_matches("inspect") {
+        let path = inspect_matches.value_of("INPUT").unwrap();
+        match inspect::inspect(path) {
+            Ok(reports) => {
+                if inspect_matches.value_of("format") == Some("json") {
+                    println!("{}", to_string_pretty(&reports).unwrap());
+                } else {
+                    inspect::print_table(&reports);
                 }
             }
-        }
-    }
-
-    if let Some(root_object) = metadata
-        .objects
-        .iter()
-        .find(|x| x.path == "/'Stream'")
-    {
-        for property in root_object.properties.iter() {
-            let name = property.name.clone();
-            let string_value = property.string_value.clone();
-
-            match name.as_str() {
-                "name" => stream_metadata.name = "Stream".to_string(),
-                "ChannelType" => stream_metadata.channel_type = string_value,
-                "ChannelSubType" => {
-                    stream_metadata.channel_sub_type = string_value.parse().unwrap()
-                }
-                "ChannelInfo" => stream_metadata.channel_info = string_value,
-                "Sensor" => stream_metadata.sensor = string_value,
-                "PropertiesJSON" => stream_metadata.properties_json = string_value,
-                "Codec" => stream_metadata.codec = string_value,
-                "System" => stream_metadata.system = string_value,
-                "PXIeCard" => stream_metadata.pxie_card = string_value,
-                "SampleTimestamp" => stream_metadata.sample_timestamp = string_value,
-                "SampleType" => stream_metadata.sample_type = string_value,
-                "SampleTypeVersion" => stream_metadata.sample_type_version = string_value,
-                _ => {
-                    return Err(TdmsParseError::UnexpectedProperty { property: name });
-                }
+            Err(e) => {
+                report_error(path, &e);
+                process::exit(e.category().exit_code());
             }
         }
+        return;
     }
 
-    Ok((file_metadata, stream_metadata))
-}
-
-fn is_root(x: &TdmsObject) -> bool {
-    x.path == "/"
-}
-
-fn is_valid_group(x: &TdmsObject) -> bool {
-    if is_root(x) {
-        return false;
-    }
-    let allowed_groups = ["'stream'"];
-    let group_name = get_group_name(x);
-    if let Some(name) = group_name {
-        !allowed_groups.contains(&name.to_lowercase().as_str())
-    } else {
-        false
-    }
-}
-
-fn get_group_name(object: &TdmsObject) -> Option<String> {
-    let parts = object.path.split('/').collect::<Vec<&str>>();
-    if parts.len() != 2 {
-        None
-    } else {
-        Some(parts[1].to_string())
-    }
-}
-
-fn is_valid_channel(x: &TdmsObject) -> bool {
-    if is_root(x) {
-        return false;
-    }
-    let allowed_channels = ["'data'", "'index'", "'timestamp'", "'header'", "'metadata'"];
-    let channel_name = get_channel_name(x);
-    if let Some(name) = channel_name {
-        !allowed_channels.contains(&name.to_lowercase().as_str())
-    } else {
-        false
+    if let Some(watch_matches) = matches.subcommand_matches("watch") {
+        let dirs = watch_matches
+            .values_of("DIR")
+            .unwrap()
+            .map(PathBuf::from)
+            .collect::<Vec<PathBuf>>();
+        let state_file = match watch_matches.value_of("state") {
+            Some(state_file) => PathBuf::from(state_file),
+            None => dirs[0].join(".hvds-watch-state.json"),
+        };
+        let options = WatchOptions {
+            dirs,
+            state_file,
+            settle_time: Duration::from_secs(
+                value_t!(watch_matches, "settle", u64).unwrap_or_else(|e| e.exit()),
+            ),
+            poll_interval: Duration::from_secs(
+                value_t!(watch_matches, "interval", u64).unwrap_or_else(|e| e.exit()),
+            ),
+        };
+        // every watched file gets its own RawFile name
+        let index_options = IndexOptions {
+            output_path: String::new(),
+            ..index_options(&matches, string_decoding)
+        };
+        let result = Watcher::new(options).and_then(|mut watcher| {
+            watcher.run(|path| index_file(path, &index_options).map(|_| ()))
+        });
+        if let Err(e) = result {
+            report_error("", &e);
+            process::exit(e.category().exit_code());
+        }
+        return;
     }
-}
-
-fn output(
-    path: &str,
-    output_path: &str,
-    file_metadata: FileMetadata,
-    stream_metadata: StreamMetadata,
-    data_start_pos: u64,
-    timestamps: Vec<u64>,
-    indices: Vec<i64>,
-    states: Vec<u32>,
-    frame_numbers: Vec<u32>
-) -> Result<(), TdmsParseError> {
-    // output to a file with json Format
-    let mut output_json_file = PathBuf::from(path);
-    output_json_file.set_extension("hvds.json");
 
-    // process index channel and timestamp channel
-    let count = indices.len() / 2;
-    if count != timestamps.len() / 4 || count != states.len() {
-        return Err(TdmsParseError::ChannelLengthMismatch {
-            index: indices.len(),
-            timestamp: timestamps.len(),
-            header: states.len()
+    if let Some(chain_matches) = matches.subcommand_matches("chain") {
+        let path = chain_matches.value_of("INPUT").unwrap();
+        let merged = chain_matches.value_of("merged").map(|merged| {
+            let format = match chain_matches.value_of("merged-format") {
+                Some("binary") => MergedFormat::Binary,
+                _ => MergedFormat::Json,
+            };
+            (Path::new(merged), format)
         });
+        if let Err(e) = run_chain(
+            Path::new(path),
+            chain_matches.value_of("output"),
+            merged,
+            string_decoding,
+        ) {
+            report_error(path, &e);
+            process::exit(e.category().exit_code());
+        }
+        return;
     }
-    let mut byte_offset: Vec<u64> = Vec::with_capacity(count);
-    let mut frame_size: Vec<u64> = Vec::with_capacity(count);
-    let mut timestamp: Vec<u64> = Vec::with_capacity(count);
-    let mut state: Vec<u32> = Vec::with_capacity(count);
-    let mut frame_number: Vec<u32> = Vec::with_capacity(count);
 
-    for i in 0..count {
-        let mut start_offset = indices[2 * i];
-        if start_offset < 0 {
-            start_offset = start_offset.abs();
-        }
-        let mut end_offset = indices[2 * i + 1].abs();
-        if end_offset < 0 {
-            end_offset = end_offset.abs();
+    if let Some(extract_matches) = matches.subcommand_matches("extract") {
+        let path = extract_matches.value_of("INPUT").unwrap();
+        let selection = FrameSelection {
+            frame_numbers: match (
+                optional_value(extract_matches, "first-frame"),
+                optional_value(extract_matches, "last-frame"),
+            ) {
+                (None, None) => None,
+                (first, last) => Some(first.unwrap_or(0)..=last.unwrap_or(u32::MAX)),
+            },
+            time_range: match (
+                optional_value(extract_matches, "start-time"),
+                optional_value(extract_matches, "end-time"),
+            ) {
+                (None, None) => None,
+                (start, end) => Some(start.unwrap_or(0)..=end.unwrap_or(u64::MAX)),
+            },
+            state: optional_value(extract_matches, "state"),
+        };
+        let target = match extract_matches.value_of("out-dir") {
+            Some(dir) => ExtractTarget::Directory(PathBuf::from(dir)),
+            None => ExtractTarget::Concatenated(PathBuf::from(
+                extract_matches.value_of("output").unwrap(),
+            )),
+        };
+        let index_path = extract_matches.value_of("index").map(Path::new);
+        match extract_with(Path::new(path), index_path, &selection, &target, string_decoding) {
+            Ok(count) => info!("{} frames extracted", count),
+            Err(e) => {
+                report_error(path, &e);
+                process::exit(e.category().exit_code());
+            }
         }
-
-        trace!("start_offset = {}, data_start_pos = {}", start_offset, data_start_pos);
-
-        byte_offset.push(start_offset as u64 + data_start_pos);
-        frame_size.push((end_offset - start_offset) as u64);
-        timestamp.push(timestamps[4 * i]);
-        state.push(states[i]);
-        frame_number.push(frame_numbers[i]);
+        return;
     }
-    let output_data = Data {
-        byte_offset,
-        frame_size,
-        timestamp,
-        state,
-        frame_number
-    };
 
-    let output_stream = Stream {
-        metadata: stream_metadata,
-        data: output_data,
-    };
-
-    let output_file_path = if output_path == "" {
-        PathBuf::from(path)
+    let inputs = matches.values_of("INPUT").unwrap().collect::<Vec<&str>>();
+    let options = index_options(&matches, string_decoding);
+    let jobs = if matches.is_present("jobs") {
+        value_t!(matches, "jobs", usize)
+            .unwrap_or_else(|e| e.exit())
+            .max(1)
     } else {
-        PathBuf::from(output_path)
-    };
-    let file_name = output_file_path.file_name().unwrap();
-    let output_file = output::File {
-        raw_file: String::from(file_name.to_str().unwrap()),
-        metadata: file_metadata,
-        stream: output_stream,
-    };
-    let output = Output {
-        schema: "http://audi.de/adas/logging/hvds/V0_0_1".to_string(),
-        file: output_file,
+        thread::available_parallelism().map_or(1, |n| n.get())
     };
-    to_writer_pretty(&File::create(output_json_file)?, &output).unwrap();
-    Ok(())
-}
+    let single_file = inputs.len() == 1 && Path::new(inputs[0]).is_file();
 
-fn read_headers(
-    mut reader: &mut BufReader<&File>,
-    lead_in: &LeadIn,
-    metadata: &Metadata,
-) -> Result<(Vec<u32>, Vec<u32>), TdmsParseError> {
-    // Frame 1: State
-    // Frame 1: FrameNumber,
-    // Frame 2: State,
-    // Frame 2: FrameNumber
-    let mut states: Vec<u32> = vec![];
-    let mut frame_numbers: Vec<u32> = vec![];
-    if let RawDataIndex::NewDataIndex(new_raw_data_index) =
-    metadata.objects[0].raw_data_index.clone()
-    {
-        let count = new_raw_data_index.chunk_size / 2;
-        states.reserve(count as usize);
-        frame_numbers.reserve(count as usize);
-        for _ in 0..count {
-            let state = read_u32(&mut reader, lead_in.is_toc_big_endian);
-            let frame_number = read_u32(&mut reader, lead_in.is_toc_big_endian);
-            trace!("{:?}", state);
-            trace!("{:?}", frame_number);
-            states.push(state);
-            frame_numbers.push(frame_number);
+    let files = match batch::collect_inputs(&inputs) {
+        Ok(files) => files,
+        Err(e) => {
+            report_error("", &e);
+            process::exit(e.category().exit_code());
         }
-    } else {
-        return Err(TdmsParseError::CannotReadHeaderChannel);
-    }
-
-    Ok((states, frame_numbers))
-}
+    };
 
-fn read_indices(
-    mut reader: &mut BufReader<&File>,
-    lead_in: &LeadIn,
-    metadata: &Metadata,
-) -> Result<Vec<i64>, TdmsParseError> {
-    let mut indices: Vec<i64> = vec![];
-    if let RawDataIndex::NewDataIndex(new_raw_data_index) =
-        metadata.objects[0].raw_data_index.clone()
-    {
-        let index_count = new_raw_data_index.chunk_size / 2;
-        indices.reserve((index_count * 2) as usize);
-        for _ in 0..index_count {
-            let index_start = read_i64(&mut reader, lead_in.is_toc_big_endian);
-            let index_end = read_i64(&mut reader, lead_in.is_toc_big_endian);
-            trace!("{:?}", index_start);
-            trace!("{:?}", index_end);
-            indices.push(index_start);
-            indices.push(index_end);
+    let incremental = matches.is_present("incremental");
+    let results = batch::run(&files, jobs, |path| {
+        let output = if incremental {
+            let (output, count) = index_incremental(path, &options)?;
+            info!("{} new frames in {}", count, path.display());
+            output
+        } else {
+            index_file(path, &options)?
+        };
+        if let Some(diagnostics) = &output.file.stream.diagnostics {
+            println!("{}:\n{}", path.display(), diagnostics);
         }
-    } else {
-        return Err(TdmsParseError::CannotReadIndexChannel);
+        Ok(())
+    });
+    if !single_file {
+        batch::print_summary(&results);
     }
-
-    Ok(indices)
-}
-
-fn read_timestamps(
-    mut reader: &mut BufReader<&File>,
-    lead_in: &LeadIn,
-    metadata: &Metadata,
-) -> Result<Vec<u64>, TdmsParseError> {
-    // In the Timestamp channel, each frame has 4 u64 timestamps.
-    // Relative Start Timestamp -> Unified hardware timestamps
-    // Relative End Timestamp
-    // Absolute Start Timestamp -> Unified hardware timestamps
-    // Absolute End Timestamp
-    let mut timestamps: Vec<u64> = vec![];
-    debug!(
-        "reading timestamps {:?}",
-        metadata.objects[0].raw_data_index.clone()
-    );
-    if let Some(object) = get_raw_data_index(&metadata) {
-        if let RawDataIndex::NewDataIndex(new_raw_data_index) = object.raw_data_index.clone() {
-            let timestamp_count = new_raw_data_index.chunk_size / 4;
-            timestamps.reserve((timestamp_count * 4) as usize);
-            for _ in 0..timestamp_count {
-                let relative_start = read_unified_timestamp(&mut reader, lead_in.is_toc_big_endian);
-                let relative_end = read_unified_timestamp(&mut reader, lead_in.is_toc_big_endian);
-                let absolute_start = read_unified_timestamp(&mut reader, lead_in.is_toc_big_endian);
-                let absolute_end = read_unified_timestamp(&mut reader, lead_in.is_toc_big_endian);
-                trace!("{:?}", relative_start);
-                trace!("{:?}", Utc.timestamp_nanos(relative_start as i64));
-                trace!("{:?}", relative_end);
-                trace!("{:?}", Utc.timestamp_nanos(relative_end as i64));
-                trace!("{:?}", absolute_start);
-                trace!("{:?}", Utc.timestamp_nanos(absolute_start as i64));
-                trace!("{:?}", absolute_end);
-                trace!("{:?}", Utc.timestamp_nanos(absolute_end as i64));
-                timestamps.push(relative_start);
-                timestamps.push(relative_end);
-                timestamps.push(absolute_start);
-                timestamps.push(absolute_end);
+    let mut exit_code = EXIT_SUCCESS;
+    for result in results.iter() {
+        if let Some(e) = &result.error {
+            report_error(&result.path.to_string_lossy(), e);
+            if exit_code == EXIT_SUCCESS {
+                exit_code = e.category().exit_code();
             }
-        } else {
-            return Err(TdmsParseError::CannotReadTimestampChannel);
         }
-    } else {
-        return Err(TdmsParseError::CannotReadTimestampChannel);
-    }
-    Ok(timestamps)
-}
-
-fn get_raw_data_index(metadata: &Metadata) -> Option<&TdmsObject> {
-    metadata.objects.iter().find(|x| is_timestamp_channel(x))
-}
-
-fn is_timestamp_channel(tdms_object: &TdmsObject) -> bool {
-    if let Some(name) = get_channel_name(tdms_object) {
-        name.to_lowercase() == "'timestamp'"
-    } else {
-        return false;
     }
+    process::exit(exit_code);
 }
 
-fn get_channel_name(object: &TdmsObject) -> Option<String> {
-    let parts = object.path.split('/').collect::<Vec<&str>>();
-    if parts.len() != 3 {
-        None
-    } else {
-        Some(parts[2].to_string())
+/// The indexing options of the top-level arguments, exits if the state
+/// definitions cannot be loaded.
+fn index_options(matches: &ArgMatches, string_decoding: StringDecoding) -> IndexOptions {
+    let state_decoder = match matches.value_of("state-definitions") {
+        Some(path) => match StateDecoder::load(Path::new(path)) {
+            Ok(decoder) => Some(decoder),
+            Err(e) => {
+                report_error(path, &e);
+                process::exit(e.category().exit_code());
+            }
+        },
+        None => None,
+    };
+    IndexOptions {
+        output_path: matches.value_of("output").unwrap_or("").to_string(),
+        diagnostics: matches.is_present("diagnostics"),
+        state_decoder,
+        absolute_time: matches.is_present("absolute-time"),
+        string_decoding,
     }
 }
 
-fn read_segment_header(
-    mut reader: &mut BufReader<&File>,
-) -> Result<(LeadIn, Metadata), TdmsParseError> {
-    let lead_in = LeadIn::read(&mut reader)?;
-    debug!("{:?}", lead_in);
+/// Writes the recording index and optionally the merged index of the chain
+/// containing `path`, failing if the chain has issues.
+fn run_chain(
+    path: &Path,
+    output: Option<&str>,
+    merged: Option<(&Path, MergedFormat)>,
+    strings: StringDecoding,
+) -> Result<(), TdmsParseError> {
+    let (splits, issues) = chain::follow_chain_with(path, strings)?;
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => chain::recording_index_path(&splits).ok_or_else(|| chain::empty_chain(path))?,
+    };
+    let recording = chain::recording(&splits, issues).ok_or_else(|| chain::empty_chain(path))?;
+    chain::print_recording(&recording);
 
-    if lead_in.contains_metadata {
-        let metadata = Metadata::read(&mut reader, lead_in.is_toc_big_endian)?;
-        debug!("{:?}", metadata);
-        Ok((lead_in, metadata))
-    } else {
-        return Err(TdmsParseError::NoMetadata);
+    let issues = recording.issues.len();
+    chain::write_recording(&output, recording)?;
+    if let Some((merged, format)) = merged {
+        MergedIndex::merge(splits).write(merged, format)?;
+    }
+    if issues > 0 {
+        return Err(TdmsParseError::BrokenSplitChain { issues });
     }
+    Ok(())
 }
 
-fn get_current_read_pos(reader: &mut BufReader<&File>) -> Result<u64, TdmsParseError> {
-    let pos = reader.seek(SeekFrom::Current(0))?;
-    Ok(pos)
+/// Parses an optional numeric option, exiting with a usage error if it is malformed.
+fn optional_value<T>(matches: &ArgMatches, name: &str) -> Option<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    matches
+        .value_of(name)
+        .map(|_| value_t!(matches, name, T).unwrap_or_else(|e| e.exit()))
 }
 
-fn init_log(path: String) -> Result<Handle, SetLoggerError> {
-    let stderr = ConsoleAppender::builder().target(Target::Stderr).build();
-
-    let mut log_file_name = PathBuf::from(path);
-    log_file_name.set_extension("log");
-    let logfile = FileAppender::builder().build(log_file_name).unwrap();
-
-    let config = Config::builder()
-        .appender(Appender::builder().build("logfile", Box::new(logfile)))
-        .appender(Appender::builder().build("stderr", Box::new(stderr)))
-        .build(
-            Root::builder()
-                .appender("logfile")
-                .appender("stderr")
-                .build(LevelFilter::Debug),
-        )
-        .unwrap();
-
-    Ok(log4rs::init_config(config).unwrap())
+/// Prints a single JSON line describing the failure on stderr.
+fn report_error(path: &str, e: &TdmsParseError) {
+    let category = e.category();
+    eprintln!(
+        "{}",
+        json!({
+            "file": path,
+            "code": category.exit_code(),
+            "category": category.name(),
+            "message": e.to_string(),
+        })
+    );
 }
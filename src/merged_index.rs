@@ -0,0 +1,282 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+use serde_json::to_writer_pretty;
+
+use crate::chain::Split;
+use crate::output::{ByteRange, Data, FragmentedFrame, StreamMetadata};
+use crate::tdms_parse_error::TdmsParseError;
+
+pub const MERGED_SCHEMA_ID: &str = "http://audi.de/adas/logging/hvds/merged/V0_0_1";
+
+/// Magic number starting a binary merged index ("HVDM").
+pub const MERGED_MAGIC: &[u8; 4] = b"HVDM";
+
+pub const MERGED_BINARY_VERSION: u32 = 1;
+
+/// Size of a frame record in the binary merged index.
+pub const MERGED_RECORD_SIZE: usize = 40;
+
+const FLAG_INVALID: u32 = 1 << 2;
+const FLAG_FRAGMENTED: u32 = 1 << 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergedFormat {
+    Json,
+    Binary,
+}
+
+/// The frames of all splits of a recording as one continuous stream.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergedIndex {
+    pub schema: String,
+
+    #[serde(rename = "ReferenceFileName")]
+    pub reference_file_name: String,
+
+    /// File names of the splits in recording order, referenced by the `Split` column.
+    #[serde(rename = "Splits")]
+    pub splits: Vec<String>,
+
+    /// Metadata of the stream in the first split.
+    #[serde(rename = "Metadata")]
+    pub metadata: StreamMetadata,
+
+    #[serde(rename = "Data")]
+    pub data: MergedData,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MergedData {
+    /// Index into `Splits` of the file holding each frame.
+    #[serde(rename = "Split")]
+    pub split: Vec<u32>,
+
+    /// The frame columns, byte offsets are relative to the frame's split and
+    /// rows of `InvalidFrames`/`FragmentedFrames` to the merged index.
+    #[serde(flatten)]
+    pub frames: Data,
+}
+
+impl MergedIndex {
+    /// Concatenates the frames of the splits, in the given order.
+    pub fn merge(splits: Vec<Split>) -> Self {
+        let mut index = MergedIndex {
+            schema: MERGED_SCHEMA_ID.to_string(),
+            reference_file_name: String::new(),
+            splits: vec![],
+            metadata: StreamMetadata::new(),
+            data: MergedData::default(),
+        };
+        for (i, split) in splits.into_iter().enumerate() {
+            let file = split.output.file;
+            if i == 0 {
+                index.reference_file_name = file.metadata.reference_file_name;
+                index.metadata = file.stream.metadata;
+            }
+            let name = split
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            index.splits.push(name);
+
+            let data = file.stream.data;
+            index
+                .data
+                .split
+                .extend(std::iter::repeat_n(i as u32, data.len()));
+            index.data.frames.append(data);
+        }
+        index
+    }
+
+    pub fn write(&self, path: &Path, format: MergedFormat) -> Result<(), TdmsParseError> {
+        let output_error = |message: String| TdmsParseError::OutputError {
+            path: path.to_string_lossy().to_string(),
+            message,
+        };
+        let file = File::create(path).map_err(|e| output_error(e.to_string()))?;
+        let mut writer = BufWriter::new(file);
+        match format {
+            MergedFormat::Json => {
+                to_writer_pretty(&mut writer, self).map_err(|e| output_error(e.to_string()))?
+            }
+            MergedFormat::Binary => self
+                .write_binary(&mut writer)
+                .map_err(|e| output_error(e.to_string()))?,
+        }
+        writer.flush().map_err(|e| output_error(e.to_string()))
+    }
+
+    /// Writes the binary merged index, all numbers are little-endian:
+    ///
+    /// | Bytes        | Content                                              |
+    /// | ------------ | ---------------------------------------------------- |
+    /// | 4            | magic `HVDM`                                         |
+    /// | 4            | version (u32)                                        |
+    /// | 4            | number of splits (u32)                               |
+    /// | 8            | number of frames (u64)                               |
+    /// | per split    | name length (u32), UTF-8 name                        |
+    /// | 40 per frame | split (u32), frame number (u32), state (u32),        |
+    /// |              | flags (u32), byte offset (u64), frame size (u64),    |
+    /// |              | relative start timestamp (u64)                       |
+    /// | 8            | number of fragmented frames (u64)                    |
+    /// | per fragment | row (u64), number of ranges (u32),                   |
+    /// |              | offset (u64) and size (u64) per range                |
+    ///
+    /// Bits 0-1 of the flags hold the `TimestampQuality` bits, bit 2 is set for
+    /// invalid and bit 3 for fragmented frames.
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let data = &self.data.frames;
+        writer.write_all(MERGED_MAGIC)?;
+        writer.write_u32::<LittleEndian>(MERGED_BINARY_VERSION)?;
+        writer.write_u32::<LittleEndian>(self.splits.len() as u32)?;
+        writer.write_u64::<LittleEndian>(data.len() as u64)?;
+        for name in self.splits.iter() {
+            writer.write_u32::<LittleEndian>(name.len() as u32)?;
+            writer.write_all(name.as_bytes())?;
+        }
+
+        let mut invalid = data.invalid_frames.iter().map(|frame| frame.row).peekable();
+        let mut fragmented = data
+            .fragmented_frames
+            .iter()
+            .map(|frame| frame.row)
+            .peekable();
+        for row in 0..data.len() {
+            let mut flags = data.timestamp_quality.get(row).copied().unwrap_or(0) as u32;
+            if invalid.next_if_eq(&row).is_some() {
+                flags |= FLAG_INVALID;
+            }
+            if fragmented.next_if_eq(&row).is_some() {
+                flags |= FLAG_FRAGMENTED;
+            }
+            writer.write_u32::<LittleEndian>(self.data.split[row])?;
+            writer.write_u32::<LittleEndian>(data.frame_number[row])?;
+            writer.write_u32::<LittleEndian>(data.state[row])?;
+            writer.write_u32::<LittleEndian>(flags)?;
+            writer.write_u64::<LittleEndian>(data.byte_offset[row])?;
+            writer.write_u64::<LittleEndian>(data.frame_size[row])?;
+            writer.write_u64::<LittleEndian>(data.timestamp[row])?;
+        }
+
+        writer.write_u64::<LittleEndian>(data.fragmented_frames.len() as u64)?;
+        for frame in data.fragmented_frames.iter() {
+            writer.write_u64::<LittleEndian>(frame.row as u64)?;
+            writer.write_u32::<LittleEndian>(frame.ranges.len() as u32)?;
+            for range in frame.ranges.iter() {
+                writer.write_u64::<LittleEndian>(range.offset)?;
+                writer.write_u64::<LittleEndian>(range.size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A frame of a binary merged index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedFrame {
+    pub split: u32,
+    pub frame_number: u32,
+    pub state: u32,
+    pub flags: u32,
+    pub byte_offset: u64,
+    pub frame_size: u64,
+    pub timestamp: u64,
+}
+
+impl MergedFrame {
+    pub fn is_invalid(&self) -> bool {
+        self.flags & FLAG_INVALID != 0
+    }
+
+    pub fn is_fragmented(&self) -> bool {
+        self.flags & FLAG_FRAGMENTED != 0
+    }
+}
+
+/// Contents of a binary merged index as read by [`read_binary`].
+#[derive(Debug)]
+pub struct MergedBinary {
+    pub splits: Vec<String>,
+    pub frames: Vec<MergedFrame>,
+    pub fragmented_frames: Vec<FragmentedFrame>,
+}
+
+/// Reads a binary merged index written by [`MergedIndex::write_binary`].
+pub fn read_binary(path: &Path) -> Result<MergedBinary, TdmsParseError> {
+    let file = File::open(path)?;
+    let file_length = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    let version = reader.read_u32::<LittleEndian>()?;
+    if &magic != MERGED_MAGIC || version != MERGED_BINARY_VERSION {
+        return Err(TdmsParseError::GeneralError {
+            message: format!(
+                "{} is not a binary merged index of version {}",
+                path.display(),
+                MERGED_BINARY_VERSION
+            ),
+        });
+    }
+
+    let split_count = reader.read_u32::<LittleEndian>()?;
+    let frame_count = reader.read_u64::<LittleEndian>()?;
+    let mut splits = Vec::with_capacity(capacity(split_count as u64, 4, file_length));
+    for _ in 0..split_count {
+        let length = reader.read_u32::<LittleEndian>()? as u64;
+        let mut name = vec![];
+        (&mut reader).take(length).read_to_end(&mut name)?;
+        if (name.len() as u64) < length {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        splits.push(String::from_utf8_lossy(&name).to_string());
+    }
+
+    let record_size = MERGED_RECORD_SIZE as u64;
+    let mut frames = Vec::with_capacity(capacity(frame_count, record_size, file_length));
+    for _ in 0..frame_count {
+        frames.push(MergedFrame {
+            split: reader.read_u32::<LittleEndian>()?,
+            frame_number: reader.read_u32::<LittleEndian>()?,
+            state: reader.read_u32::<LittleEndian>()?,
+            flags: reader.read_u32::<LittleEndian>()?,
+            byte_offset: reader.read_u64::<LittleEndian>()?,
+            frame_size: reader.read_u64::<LittleEndian>()?,
+            timestamp: reader.read_u64::<LittleEndian>()?,
+        });
+    }
+
+    let fragmented_count = reader.read_u64::<LittleEndian>()?;
+    let mut fragmented_frames = Vec::with_capacity(capacity(fragmented_count, 12, file_length));
+    for _ in 0..fragmented_count {
+        let row = reader.read_u64::<LittleEndian>()? as usize;
+        let range_count = reader.read_u32::<LittleEndian>()?;
+        let mut ranges = Vec::with_capacity(capacity(range_count as u64, 16, file_length));
+        for _ in 0..range_count {
+            ranges.push(ByteRange {
+                offset: reader.read_u64::<LittleEndian>()?,
+                size: reader.read_u64::<LittleEndian>()?,
+            });
+        }
+        fragmented_frames.push(FragmentedFrame { row, ranges });
+    }
+
+    Ok(MergedBinary {
+        splits,
+        frames,
+        fragmented_frames,
+    })
+}
+
+/// Capacity for `count` records of at least `size` bytes. The counts are read
+/// from the file, bounding them by its length keeps a corrupted count from
+/// allocating more than the file can hold.
+fn capacity(count: u64, size: u64, file_length: u64) -> usize {
+    count.min(file_length / size) as usize
+}
@@ -1,70 +1,84 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize)]
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostics;
+use crate::state_decoder::StateSummary;
+
+/// Values of the string channels of the stream by channel name, one per frame.
+pub type Annotations = BTreeMap<String, Vec<String>>;
+
+pub const SCHEMA_ID: &str = "http://audi.de/adas/logging/hvds/V0_0_1";
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Output {
     pub schema: String,
-    #[serde(rename(serialize = "File"))]
+    #[serde(rename = "File")]
     pub file: File,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct File {
-    #[serde(rename(serialize = "RawFile"))]
+    #[serde(rename = "RawFile")]
     pub raw_file: String,
-    #[serde(rename(serialize = "Metadata"))]
+    #[serde(rename = "Metadata")]
     pub metadata: FileMetadata,
-    #[serde(rename(serialize = "Stream"))]
+    #[serde(rename = "Stream")]
     pub stream: Stream,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileMetadata {
-    #[serde(rename(serialize = "Name"))]
+    #[serde(rename = "Name")]
     pub name: String,
     
-    #[serde(rename(serialize = "LoggerVersionMajor"))]
+    #[serde(rename = "LoggerVersionMajor")]
     pub logger_version_major: u32,
     
-    #[serde(rename(serialize = "LoggerVersionMinor"))]
+    #[serde(rename = "LoggerVersionMinor")]
     pub logger_version_minor: u32,
 
-    #[serde(rename(serialize = "LoggerVersionBuild"))]
+    #[serde(rename = "LoggerVersionBuild")]
     pub logger_version_build: u32,
     
-    #[serde(rename(serialize = "LoggerVersionJSON"))]
+    #[serde(rename = "LoggerVersionJSON")]
     pub logger_version_json: String,
 
-    #[serde(rename(serialize = "TDMSVersionMajor"))]
+    #[serde(rename = "TDMSVersionMajor")]
     pub tdms_version_major: u32,
 
-    #[serde(rename(serialize = "TDMSVersionMinor"))]
+    #[serde(rename = "TDMSVersionMinor")]
     pub tdms_version_minor: u32,
 
-    #[serde(rename(serialize = "CarModel"))]
+    /// Highest TDMS file format version of the segments, 4712 (1.0) or 4713 (2.0).
+    #[serde(rename = "TDMSFileVersion", default)]
+    pub tdms_file_version: u32,
+
+    #[serde(rename = "CarModel")]
     pub car_model: String,
 
-    #[serde(rename(serialize = "CarLicensePlate"))]
+    #[serde(rename = "CarLicensePlate")]
     pub car_license_plate: String,
 
-    #[serde(rename(serialize = "ReferenceFileName"))]
+    #[serde(rename = "ReferenceFileName")]
     pub reference_file_name: String,
 
-    #[serde(rename(serialize = "ReferenceSplitBefore"))]
+    #[serde(rename = "ReferenceSplitBefore")]
     pub reference_split_before: String,
 
-    #[serde(rename(serialize = "ReferenceSplitAfter"))]
+    #[serde(rename = "ReferenceSplitAfter")]
     pub reference_split_after: String,
 
-    #[serde(rename(serialize = "ReferenceSplitStartTime"))]
+    #[serde(rename = "ReferenceSplitStartTime")]
     pub reference_split_start_time: u64,
 
-    #[serde(rename(serialize = "ReferenceSplitStopTime"))]
+    #[serde(rename = "ReferenceSplitStopTime")]
     pub reference_split_stop_time: u64,
 
-    #[serde(rename(serialize = "FutureTimeEvent"))]
+    #[serde(rename = "FutureTimeEvent")]
     pub future_time_event: u64,
 
-    #[serde(rename(serialize = "ZFramePath"))]
+    #[serde(rename = "ZFramePath")]
     pub zframe_path: String,
 }
 
@@ -78,6 +92,7 @@ impl FileMetadata {
             logger_version_json: String::new(),
             tdms_version_major: 0,
             tdms_version_minor: 0,
+            tdms_file_version: 0,
             car_model: String::new(),
             car_license_plate: String::new(),
             reference_file_name: String::new(),
@@ -91,42 +106,48 @@ impl FileMetadata {
     }
 }
 
-#[derive(Debug, Serialize)]
+impl Default for FileMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct StreamMetadata {
-    #[serde(rename(serialize = "Name"))]
+    #[serde(rename = "Name")]
     pub name: String,
 
-    #[serde(rename(serialize = "ChannelType"))]
+    #[serde(rename = "ChannelType")]
     pub channel_type: String,
     
-    #[serde(rename(serialize = "ChannelSubType"))]
+    #[serde(rename = "ChannelSubType")]
     pub channel_sub_type: String,
 
-    #[serde(rename(serialize = "ChannelInfo"))]
+    #[serde(rename = "ChannelInfo")]
     pub channel_info: String,
     
-    #[serde(rename(serialize = "Sensor"))]
+    #[serde(rename = "Sensor")]
     pub sensor: String,
     
-    #[serde(rename(serialize = "PropertiesJSON"))]
+    #[serde(rename = "PropertiesJSON")]
     pub properties_json: String,
     
-    #[serde(rename(serialize = "Codec"))]
+    #[serde(rename = "Codec")]
     pub codec: String,
     
-    #[serde(rename(serialize = "System"))]
+    #[serde(rename = "System")]
     pub system: String,
     
-    #[serde(rename(serialize = "PXIeCard"))]
+    #[serde(rename = "PXIeCard")]
     pub pxie_card: String,
     
-    #[serde(rename(serialize = "SampleTimestamp"))]
+    #[serde(rename = "SampleTimestamp")]
     pub sample_timestamp: String,
     
-    #[serde(rename(serialize = "SampleType"))]
+    #[serde(rename = "SampleType")]
     pub sample_type: String,
     
-    #[serde(rename(serialize = "SampleTypeVersion"))]
+    #[serde(rename = "SampleTypeVersion")]
     pub sample_type_version: String,
 }
 
@@ -149,24 +170,200 @@ impl StreamMetadata {
     }
 }
 
-#[derive(Debug, Serialize)]
+impl Default for StreamMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Stream {
-    #[serde(rename(serialize = "Metadata"))]
+    #[serde(rename = "Metadata")]
     pub metadata: StreamMetadata,
-    #[serde(rename(serialize = "Data"))]
+    #[serde(rename = "Data")]
     pub data: Data,
+    #[serde(rename = "Diagnostics", default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Diagnostics>,
+    #[serde(rename = "StateSummary", default, skip_serializing_if = "Option::is_none")]
+    pub state_summary: Option<StateSummary>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Data {
-    #[serde(rename(serialize = "FrameByteOffset"))]
+    #[serde(rename = "FrameByteOffset")]
     pub byte_offset: Vec<u64>,
-    #[serde(rename(serialize = "FrameSize"))]
+    #[serde(rename = "FrameSize")]
     pub frame_size: Vec<u64>,
-    #[serde(rename(serialize = "Timestamp"))]
+    #[serde(rename = "Timestamp")]
     pub timestamp: Vec<u64>,
-    #[serde(rename(serialize = "State"))]
+    /// Raw value of the two high bits of the relative start timestamp.
+    #[serde(rename = "TimestampQuality", default)]
+    pub timestamp_quality: Vec<u8>,
+    #[serde(rename = "AbsoluteTimestamp", default, skip_serializing_if = "Vec::is_empty")]
+    pub absolute_timestamp: Vec<u64>,
+    #[serde(rename = "AbsoluteTime", default, skip_serializing_if = "Vec::is_empty")]
+    pub absolute_time: Vec<String>,
+    #[serde(rename = "State")]
     pub state: Vec<u32>,
-    #[serde(rename(serialize = "FrameNumber"))]
-    pub frame_number: Vec<u32>
+    #[serde(rename = "FrameNumber")]
+    pub frame_number: Vec<u32>,
+    /// Frame annotations written to string channels of the stream, see `Annotations`.
+    #[serde(rename = "Annotations", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: Annotations,
+    #[serde(rename = "InvalidFrames", default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_frames: Vec<InvalidFrame>,
+    #[serde(rename = "FragmentedFrames", default, skip_serializing_if = "Vec::is_empty")]
+    pub fragmented_frames: Vec<FragmentedFrame>
+}
+
+/// A frame whose payload straddles the boundary between data segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FragmentedFrame {
+    #[serde(rename = "Row")]
+    pub row: usize,
+    /// The parts of the payload in file order, the first starts at `FrameByteOffset`.
+    #[serde(rename = "Ranges")]
+    pub ranges: Vec<ByteRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    #[serde(rename = "Offset")]
+    pub offset: u64,
+    #[serde(rename = "Size")]
+    pub size: u64,
+}
+
+/// Why the Index channel entry of a frame cannot be used as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameIssue {
+    /// The start or end offset is negative. `FrameByteOffset` and `FrameSize`
+    /// are taken from the absolute values.
+    NegativeOffset,
+    /// The end offset lies before the start offset, `FrameSize` is 0.
+    EndBeforeStart,
+    /// The payload ends past the end of the Data channel, `FrameSize` and the
+    /// byte ranges only cover the bytes of the Data channel.
+    PastDataEnd,
+}
+
+/// A frame whose Index channel entry has negative or reversed offsets or lies
+/// past the end of the Data channel, with the raw signed offsets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidFrame {
+    #[serde(rename = "Row")]
+    pub row: usize,
+    #[serde(rename = "StartOffset")]
+    pub start_offset: i64,
+    #[serde(rename = "EndOffset")]
+    pub end_offset: i64,
+    #[serde(rename = "Issue")]
+    pub issue: FrameIssue,
+}
+
+impl Data {
+    pub fn with_capacity(count: usize) -> Self {
+        Data {
+            byte_offset: Vec::with_capacity(count),
+            frame_size: Vec::with_capacity(count),
+            timestamp: Vec::with_capacity(count),
+            timestamp_quality: Vec::with_capacity(count),
+            absolute_timestamp: vec![],
+            absolute_time: vec![],
+            state: Vec::with_capacity(count),
+            frame_number: Vec::with_capacity(count),
+            annotations: Annotations::new(),
+            invalid_frames: vec![],
+            fragmented_frames: vec![],
+        }
+    }
+
+    pub fn truncate(&mut self, count: usize) {
+        self.byte_offset.truncate(count);
+        self.frame_size.truncate(count);
+        self.timestamp.truncate(count);
+        self.timestamp_quality.truncate(count);
+        self.absolute_timestamp.truncate(count);
+        self.absolute_time.truncate(count);
+        self.state.truncate(count);
+        self.frame_number.truncate(count);
+        for values in self.annotations.values_mut() {
+            values.truncate(count);
+        }
+        self.invalid_frames.retain(|frame| frame.row < count);
+        self.fragmented_frames.retain(|frame| frame.row < count);
+    }
+
+    pub fn len(&self) -> usize {
+        self.byte_offset.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.byte_offset.is_empty()
+    }
+
+    /// Appends the frames of `other`. The absolute times are only kept if both have them.
+    pub fn append(&mut self, mut other: Data) {
+        let rows = self.len();
+        let has_absolute_times = |data: &Data| data.absolute_timestamp.len() == data.len();
+        if has_absolute_times(self) && has_absolute_times(&other) {
+            self.absolute_timestamp.append(&mut other.absolute_timestamp);
+            self.absolute_time.append(&mut other.absolute_time);
+        } else {
+            self.absolute_timestamp.clear();
+            self.absolute_time.clear();
+        }
+
+        self.byte_offset.append(&mut other.byte_offset);
+        self.frame_size.append(&mut other.frame_size);
+        self.timestamp.append(&mut other.timestamp);
+        self.timestamp_quality.append(&mut other.timestamp_quality);
+        self.state.append(&mut other.state);
+        self.frame_number.append(&mut other.frame_number);
+        for (name, mut values) in other.annotations {
+            self.annotations
+                .entry(name)
+                .or_insert_with(|| vec![String::new(); rows])
+                .append(&mut values);
+        }
+        self.pad_annotations();
+        for mut frame in other.invalid_frames {
+            frame.row += rows;
+            self.invalid_frames.push(frame);
+        }
+        for mut frame in other.fragmented_frames {
+            frame.row += rows;
+            self.fragmented_frames.push(frame);
+        }
+    }
+
+    /// Fills the annotation channels missing for the last frames with empty strings.
+    pub fn pad_annotations(&mut self) {
+        let rows = self.len();
+        for values in self.annotations.values_mut() {
+            values.resize(rows, String::new());
+        }
+    }
+
+    /// File ranges holding the payload of the frame in `row`.
+    pub fn frame_ranges(&self, row: usize) -> Vec<ByteRange> {
+        match self
+            .fragmented_frames
+            .binary_search_by_key(&row, |frame| frame.row)
+        {
+            Ok(i) => self.fragmented_frames[i].ranges.clone(),
+            Err(_) => vec![ByteRange {
+                offset: self.byte_offset[row],
+                size: self.frame_size[row],
+            }],
+        }
+    }
+
+    /// The issue of the frame in `row`, `None` for valid frames.
+    pub fn frame_issue(&self, row: usize) -> Option<FrameIssue> {
+        self.invalid_frames
+            .binary_search_by_key(&row, |frame| frame.row)
+            .ok()
+            .map(|i| self.invalid_frames[i].issue)
+    }
 }
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::StreamMetadata;
+use crate::tdms_parse_error::TdmsParseError;
+
+/// Contents of a state definition file, see `schema/state_definitions.example.json`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StateDefinitions {
+    #[serde(rename = "Definitions")]
+    pub definitions: Vec<StateDefinition>,
+}
+
+/// Meaning of the bits of the `State` word for one `SampleType`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDefinition {
+    #[serde(rename = "SampleType")]
+    pub sample_type: String,
+
+    /// Applies to all versions of the sample type if not set.
+    #[serde(rename = "SampleTypeVersion", default)]
+    pub sample_type_version: Option<String>,
+
+    #[serde(rename = "Flags", default)]
+    pub flags: Vec<StateFlag>,
+
+    #[serde(rename = "Fields", default)]
+    pub fields: Vec<StateField>,
+}
+
+/// A single bit, set if the flag applies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateFlag {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Bit")]
+    pub bit: u32,
+}
+
+/// A value of `Width` bits starting at bit `Shift`, e.g. an error code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateField {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Shift")]
+    pub shift: u32,
+
+    #[serde(rename = "Width")]
+    pub width: u32,
+
+    /// Names of the known values, unnamed values are reported by number.
+    #[serde(rename = "Values", default)]
+    pub values: BTreeMap<u32, String>,
+}
+
+impl StateField {
+    pub fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - self.width)) << self.shift
+    }
+
+    pub fn value(&self, state: u32) -> u32 {
+        (state & self.mask()) >> self.shift
+    }
+}
+
+impl StateDefinition {
+    /// Bits of the state word covered by a flag or field.
+    pub fn defined_bits(&self) -> u32 {
+        let flags = self.flags.iter().fold(0, |bits, flag| bits | 1 << flag.bit);
+        self.fields
+            .iter()
+            .fold(flags, |bits, field| bits | field.mask())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for flag in self.flags.iter() {
+            if flag.bit >= 32 {
+                return Err(format!("flag {} uses bit {}", flag.name, flag.bit));
+            }
+        }
+        for field in self.fields.iter() {
+            let end = field.shift.checked_add(field.width);
+            if field.width == 0 || end.is_none_or(|end| end > 32) {
+                return Err(format!(
+                    "field {} with shift {} and width {} exceeds the 32 bit state",
+                    field.name, field.shift, field.width
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of frames per flag and field value of a stream.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateSummary {
+    #[serde(rename = "SampleType")]
+    pub sample_type: String,
+
+    #[serde(rename = "SampleTypeVersion")]
+    pub sample_type_version: Option<String>,
+
+    #[serde(rename = "FrameCount")]
+    pub frame_count: usize,
+
+    #[serde(rename = "Flags")]
+    pub flags: Vec<FlagCount>,
+
+    #[serde(rename = "Fields")]
+    pub fields: Vec<FieldSummary>,
+
+    /// Frames with bits set that are not covered by the definition.
+    #[serde(rename = "UndefinedBitsCount")]
+    pub undefined_bits_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlagCount {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Count")]
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldSummary {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Values")]
+    pub values: Vec<FieldValueCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldValueCount {
+    #[serde(rename = "Value")]
+    pub value: u32,
+
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+
+    #[serde(rename = "Count")]
+    pub count: usize,
+}
+
+/// Decodes the `State` words of a stream based on its `SampleType` and `SampleTypeVersion`.
+#[derive(Debug, Default, Clone)]
+pub struct StateDecoder {
+    definitions: Vec<StateDefinition>,
+}
+
+impl StateDecoder {
+    pub fn new(definitions: StateDefinitions) -> Result<Self, TdmsParseError> {
+        for definition in definitions.definitions.iter() {
+            definition
+                .validate()
+                .map_err(|message| TdmsParseError::GeneralError {
+                    message: format!(
+                        "invalid state definition for {}: {}",
+                        definition.sample_type, message
+                    ),
+                })?;
+        }
+        Ok(StateDecoder {
+            definitions: definitions.definitions,
+        })
+    }
+
+    /// Reads a JSON state definition file.
+    pub fn load(path: &Path) -> Result<Self, TdmsParseError> {
+        let file = File::open(path)?;
+        let definitions = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            TdmsParseError::GeneralError {
+                message: format!("invalid state definitions {}: {}", path.display(), e),
+            }
+        })?;
+        StateDecoder::new(definitions)
+    }
+
+    /// The definition for the exact version, or else the one for all versions of the sample type.
+    pub fn definition(&self, sample_type: &str, version: &str) -> Option<&StateDefinition> {
+        let mut definitions = self
+            .definitions
+            .iter()
+            .filter(|definition| definition.sample_type == sample_type);
+        definitions
+            .clone()
+            .find(|definition| definition.sample_type_version.as_deref() == Some(version))
+            .or_else(|| definitions.find(|definition| definition.sample_type_version.is_none()))
+    }
+
+    /// Counts the flags and field values of all frames of a stream, `None` if
+    /// there is no definition for its sample type.
+    pub fn summarize(&self, metadata: &StreamMetadata, states: &[u32]) -> Option<StateSummary> {
+        let definition = self.definition(&metadata.sample_type, &metadata.sample_type_version);
+        let definition = match definition {
+            Some(definition) => definition,
+            None => {
+                debug!(
+                    "no state definition for sample type {} version {}",
+                    metadata.sample_type, metadata.sample_type_version
+                );
+                return None;
+            }
+        };
+
+        let mut flag_counts = vec![0; definition.flags.len()];
+        let mut value_counts = vec![BTreeMap::new(); definition.fields.len()];
+        let undefined_bits = !definition.defined_bits();
+        let mut undefined_bits_count = 0;
+        for &state in states {
+            for (count, flag) in flag_counts.iter_mut().zip(definition.flags.iter()) {
+                if state & (1 << flag.bit) != 0 {
+                    *count += 1;
+                }
+            }
+            for (counts, field) in value_counts.iter_mut().zip(definition.fields.iter()) {
+                *counts.entry(field.value(state)).or_insert(0) += 1;
+            }
+            if state & undefined_bits != 0 {
+                undefined_bits_count += 1;
+            }
+        }
+
+        Some(StateSummary {
+            sample_type: definition.sample_type.clone(),
+            sample_type_version: definition.sample_type_version.clone(),
+            frame_count: states.len(),
+            flags: definition
+                .flags
+                .iter()
+                .zip(flag_counts)
+                .map(|(flag, count)| FlagCount {
+                    name: flag.name.clone(),
+                    count,
+                })
+                .collect(),
+            fields: definition
+                .fields
+                .iter()
+                .zip(value_counts)
+                .map(|(field, counts)| FieldSummary {
+                    name: field.name.clone(),
+                    values: counts
+                        .into_iter()
+                        .map(|(value, count)| FieldValueCount {
+                            value,
+                            name: field.values.get(&value).cloned(),
+                            count,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            undefined_bits_count,
+        })
+    }
+}
@@ -1,6 +1,11 @@
-#![allow(dead_code)]
+use std::convert::TryFrom;
 
+use crate::tdms_parse_error::TdmsParseError;
+
+/// The discriminants are the type codes of the file format, `as u32` yields the
+/// code (e.g. when writing test files) and `DAQmxRawData` only fits a `u32`.
 #[derive(Debug, PartialEq, Clone)]
+#[repr(u32)]
 pub enum TdmsDataType {
     Void,
     I8,
@@ -26,9 +31,11 @@ pub enum TdmsDataType {
     DAQmxRawData = 0xFFFFFFFF,
 }
 
-impl From<u32> for TdmsDataType {
-    fn from(input: u32) -> Self {
-        match input {
+impl TryFrom<u32> for TdmsDataType {
+    type Error = TdmsParseError;
+
+    fn try_from(input: u32) -> Result<Self, Self::Error> {
+        Ok(match input {
             0x0 => TdmsDataType::Void,
             0x1 => TdmsDataType::I8,
             0x2 => TdmsDataType::I16,
@@ -51,10 +58,45 @@ impl From<u32> for TdmsDataType {
             0x08000c => TdmsDataType::ComplexSingleFloat,
             0x10000d => TdmsDataType::ComplexDoubleFloat,
             0xFFFFFFFF => TdmsDataType::DAQmxRawData,
-            _ => {
-                error!("Unknown Property data type");
-                unreachable!()
-            }
-        }
+            code => return Err(TdmsParseError::UnknownDataType { code }),
+        })
+    }
+}
+
+/// Data type of a DAQmx scaler. DAQmx numbers its types differently from
+/// [`TdmsDataType`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum DaqmxDataType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    SingleFloat,
+    DoubleFloat,
+    TimeStamp,
+}
+
+impl TryFrom<u32> for DaqmxDataType {
+    type Error = TdmsParseError;
+
+    fn try_from(input: u32) -> Result<Self, Self::Error> {
+        Ok(match input {
+            0 => DaqmxDataType::U8,
+            1 => DaqmxDataType::I8,
+            2 => DaqmxDataType::U16,
+            3 => DaqmxDataType::I16,
+            4 => DaqmxDataType::U32,
+            5 => DaqmxDataType::I32,
+            6 => DaqmxDataType::U64,
+            7 => DaqmxDataType::I64,
+            8 => DaqmxDataType::SingleFloat,
+            9 => DaqmxDataType::DoubleFloat,
+            0xFFFFFFFF => DaqmxDataType::TimeStamp,
+            code => return Err(TdmsParseError::UnknownDaqmxDataType { code }),
+        })
     }
 }
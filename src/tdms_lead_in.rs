@@ -1,43 +1,83 @@
-#![allow(dead_code)]
-
-use byteorder::{ByteOrder, LittleEndian, NativeEndian};
+use byteorder::{ByteOrder, LittleEndian};
 use std::io::prelude::*;
 
 use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::INCOMPLETE_SEGMENT_OFFSET;
 use crate::util::{load_part, read_u32, read_u64};
 
+/// Size in bytes of the lead-in at the start of every segment.
+pub const LEAD_IN_SIZE: u64 = 28;
+
+/// The tag starting every segment, independent of the byte order of the file.
+pub const TDMS_TAG: &[u8; 4] = b"TDSm";
+
+/// Version number of the TDMS file format 1.0.
+pub const TDMS_VERSION_1_0: u32 = 4712;
+
+/// Version number of the TDMS file format 2.0.
+pub const TDMS_VERSION_2_0: u32 = 4713;
+
+/// Whether a complete lead-in starts at `pos` in a file of `file_length` bytes.
+/// Positions come from the file, so this must not overflow for any `pos`.
+pub fn has_lead_in_at(pos: u64, file_length: u64) -> bool {
+    file_length
+        .checked_sub(LEAD_IN_SIZE)
+        .is_some_and(|last| pos <= last)
+}
+
 #[derive(Debug)]
 pub struct LeadIn {
-    toc_mask: u32,
-    version: u32,
+    pub toc_mask: u32,
+    pub version: u32,
     pub next_segment_offset: u64,
     pub raw_data_offset: u64,
     pub contains_metadata: bool,
     pub contains_raw_data: bool,
-    contains_daqmx_raw_data: bool,
-    is_raw_data_in_segment_interleaved: bool,
+    pub contains_daqmx_raw_data: bool,
+    pub is_raw_data_in_segment_interleaved: bool,
     pub is_toc_big_endian: bool,
-    contains_new_object_list: bool,
+    pub contains_new_object_list: bool,
 }
 
 impl LeadIn {
     pub fn read<T: Read>(mut reader: &mut T) -> Result<LeadIn, TdmsParseError> {
-        let tdms_tag = NativeEndian::read_u32(&mut load_part(reader, 4));
-        let is_little_endian = crate::util::is_little_endian();
-        if (!is_little_endian && tdms_tag as u64 != 0x54444536D)
-            || (is_little_endian && tdms_tag != 1834173524)
-        {
+        let tdms_tag = load_part(reader, 4)?;
+        if tdms_tag != TDMS_TAG {
             return Err(TdmsParseError::IncorrectTdmsTag);
         }
 
-        let toc_mask = LittleEndian::read_u32(&mut load_part(reader, 4));
+        // the ToC mask is little-endian in every file, its bit 6 selects the
+        // byte order of everything else
+        let toc_mask = LittleEndian::read_u32(&load_part(reader, 4)?);
         let is_toc_big_endian = toc_mask & 64 != 0;
 
+        let version = read_u32(&mut reader, is_toc_big_endian)?;
+        if version != TDMS_VERSION_1_0 && version != TDMS_VERSION_2_0 {
+            return Err(TdmsParseError::UnsupportedTdmsVersion { version });
+        }
+        // interleaved and big-endian raw data and DAQmx raw data came with 2.0
+        if version == TDMS_VERSION_1_0 && toc_mask & (32 | 64 | 128) != 0 {
+            warn!(
+                "TDMS 1.0 segment uses features of TDMS 2.0 (ToC mask {:#x})",
+                toc_mask
+            );
+        }
+
+        let next_segment_offset = read_u64(&mut reader, is_toc_big_endian)?;
+        let raw_data_offset = read_u64(&mut reader, is_toc_big_endian)?;
+        if next_segment_offset != INCOMPLETE_SEGMENT_OFFSET && raw_data_offset > next_segment_offset
+        {
+            return Err(TdmsParseError::InvalidSegmentOffsets {
+                next_segment_offset,
+                raw_data_offset,
+            });
+        }
+
         Ok(LeadIn {
-            toc_mask: toc_mask,
-            version: read_u32(&mut reader, is_toc_big_endian),
-            next_segment_offset: read_u64(&mut reader, is_toc_big_endian),
-            raw_data_offset: read_u64(&mut reader, is_toc_big_endian),
+            toc_mask,
+            version,
+            next_segment_offset,
+            raw_data_offset,
             contains_metadata: toc_mask & 2 != 0,
             contains_new_object_list: toc_mask & 4 != 0,
             contains_raw_data: toc_mask & 8 != 0,
@@ -46,4 +86,27 @@ impl LeadIn {
             contains_daqmx_raw_data: toc_mask & 128 != 0,
         })
     }
+
+    /// Absolute file position of the segment following the one at `pos`, `None`
+    /// for an unfinished segment.
+    pub fn next_segment_pos(&self, pos: u64) -> Result<Option<u64>, TdmsParseError> {
+        if self.next_segment_offset == INCOMPLETE_SEGMENT_OFFSET {
+            return Ok(None);
+        }
+        pos.checked_add(LEAD_IN_SIZE)
+            .and_then(|pos| pos.checked_add(self.next_segment_offset))
+            .map(Some)
+            .ok_or(TdmsParseError::InvalidSegmentOffsets {
+                next_segment_offset: self.next_segment_offset,
+                raw_data_offset: self.raw_data_offset,
+            })
+    }
+
+    /// The TDMS file format version of the segment, e.g. "2.0".
+    pub fn format_version(&self) -> &'static str {
+        match self.version {
+            TDMS_VERSION_1_0 => "1.0",
+            _ => "2.0",
+        }
+    }
 }
@@ -1,18 +1,18 @@
-#![allow(dead_code)]
-#![allow(unused_assignments)]
-
-use std::convert::Into;
-
-use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::convert::TryFrom;
 
 use crate::tdms_parse_error::TdmsParseError;
 use std::io::prelude::*;
 
-use crate::util::{read_string, read_u32, read_u64};
+use crate::util::{
+    decode_string, read_bytes, read_u32, read_u64, read_u8, PositionReader, StringDecoding,
+};
 
-use crate::tdms_datatype::TdmsDataType;
+use crate::channel_decoder::read_value;
+use crate::tdms_datatype::{DaqmxDataType, TdmsDataType};
+use crate::tdms_lead_in::{LeadIn, TDMS_VERSION_2_0};
 use crate::tdms_object::{
-    DaqFormatChangingScaler, DaqmxScaler, NewRawDataIndex, RawDataIndex, TdmsObject, TdmsProperty,
+    DaqDigitalLineScaler, DaqFormatChangingScaler, DaqmxDigitalLineScaler, DaqmxScaler,
+    NewRawDataIndex, RawDataIndex, TdmsObject, TdmsProperty,
 };
 
 #[derive(Debug, Clone)]
@@ -22,115 +22,180 @@ pub struct Metadata {
 }
 
 impl Metadata {
+    /// Reads the metadata of a segment, decoding strings strictly. Offsets in
+    /// errors are relative to the start of the metadata.
     pub fn read<T: Read>(
-        mut reader: &mut T,
+        reader: &mut T,
+        is_toc_big_endian: bool,
+        version: u32,
+    ) -> Result<Metadata, TdmsParseError> {
+        let mut reader = PositionReader::new(reader, 0);
+        Self::read_from(&mut reader, is_toc_big_endian, version, StringDecoding::Strict)
+    }
+
+    /// Reads the metadata of the segment with `lead_in`, starting at the
+    /// absolute file position `metadata_pos`.
+    pub fn read_with<T: Read>(
+        reader: &mut T,
+        lead_in: &LeadIn,
+        metadata_pos: u64,
+        strings: StringDecoding,
+    ) -> Result<Metadata, TdmsParseError> {
+        let mut reader = PositionReader::new(reader, metadata_pos);
+        Self::read_from(&mut reader, lead_in.is_toc_big_endian, lead_in.version, strings)
+    }
+
+    fn read_from<T: Read>(
+        mut reader: &mut PositionReader<T>,
         is_toc_big_endian: bool,
+        version: u32,
+        strings: StringDecoding,
     ) -> Result<Metadata, TdmsParseError> {
-        let object_number = read_u32(&mut reader, is_toc_big_endian);
+        let object_number = read_u32(&mut reader, is_toc_big_endian)?;
 
         let mut objects = vec![];
         for _ in 0..object_number {
             debug!("*********** OBJECT START");
 
             /* object path */
-            let object_path_length = read_u32(&mut reader, is_toc_big_endian);
+            let object_path_length = read_u32(&mut reader, is_toc_big_endian)?;
             debug!("object_path_length = {}", object_path_length);
 
-            let object_path = read_string(&mut reader, object_path_length as usize)?;
+            let object_path_pos = reader.pos;
+            let object_path_bytes = read_bytes(&mut reader, object_path_length as usize)?;
+            let object_path = decode_string(&object_path_bytes, strings).map_err(|valid| {
+                TdmsParseError::InvalidUtf8 {
+                    path: String::from_utf8_lossy(&object_path_bytes).into_owned(),
+                    offset: object_path_pos + valid as u64,
+                }
+            })?;
             debug!("object_path = {}", object_path);
 
             /* raw data index */
             let raw_data_index: RawDataIndex;
-            let mut raw_data_index_vec = vec![0; 4];
-            reader.read(&mut raw_data_index_vec)?;
-            match raw_data_index_vec.as_slice() {
-                [0xff, 0xff, 0xff, 0xff] => {
+            // the marker is either a special value or the length of a new index
+            let raw_data_index_marker = read_u32(&mut reader, is_toc_big_endian)?;
+            match raw_data_index_marker {
+                0xffff_ffff => {
                     // No raw data assigned in this segment
                     raw_data_index = RawDataIndex::NoRawData;
                 }
-                [0x69, 0x12, 0x00, 0x00] => {
+                0x0000_1269 => {
                     let mut daqmx_data = DaqmxScaler::new();
                     // DAQmx raw data Format Changing scaler in this segment
-                    let datatype_u32 = read_u32(&mut reader, is_toc_big_endian);
+                    let datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
                     if datatype_u32 != 0xffffffff {
                         return Err(TdmsParseError::IncorrectDataTypeInDaqmxRawDataIndex);
                     }
-                    let datatype: TdmsDataType = datatype_u32.into();
+                    let datatype = TdmsDataType::try_from(datatype_u32)?;
                     daqmx_data.datatype = datatype;
 
-                    let array_dimension = read_u32(&mut reader, is_toc_big_endian); // TODO: check file version
+                    let array_dimension = read_u32(&mut reader, is_toc_big_endian)?;
+                    check_array_dimension(array_dimension, version)?;
                     daqmx_data.array_dimension = array_dimension;
 
-                    let number_of_values = read_u64(&mut reader, is_toc_big_endian);
+                    let number_of_values = read_u64(&mut reader, is_toc_big_endian)?;
                     daqmx_data.chunk_size = number_of_values;
 
                     /* vector of Format Changing scalers*/
-                    let scalers_vector_size = read_u32(&mut reader, is_toc_big_endian);
+                    let scalers_vector_size = read_u32(&mut reader, is_toc_big_endian)?;
                     daqmx_data.scalers_size = scalers_vector_size;
 
                     let mut scalers: Vec<DaqFormatChangingScaler> = vec![];
-                    for iter_number in 0..scalers_vector_size {
-                        if iter_number == 0 {
-                            let datatype_u32 = read_u32(&mut reader, is_toc_big_endian);
-                            let datatype: TdmsDataType = datatype_u32.into();
-
-                            let raw_buffer_index = read_u32(&mut reader, is_toc_big_endian);
-                            let raw_byte_offset_within_stride =
-                                read_u32(&mut reader, is_toc_big_endian);
-                            let sample_format_bitmap = read_u32(&mut reader, is_toc_big_endian);
-                            let scale_id = read_u32(&mut reader, is_toc_big_endian);
-
-                            let scaler = DaqFormatChangingScaler {
-                                size: scalers_vector_size,
-                                datatype,
-                                raw_buffer_index,
-                                raw_byte_offset_within_stride,
-                                sample_format_bitmap,
-                                scale_id,
-                            };
-                            scalers.push(scaler);
-                            // TODO: (If the vector size is larger than 1, the object contains multiple Format Changing scalers and the information in the previous bullet items can be repeated.)
-                        }
+                    for _ in 0..scalers_vector_size {
+                        let datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
+                        let datatype = DaqmxDataType::try_from(datatype_u32)?;
+
+                        let raw_buffer_index = read_u32(&mut reader, is_toc_big_endian)?;
+                        let raw_byte_offset_within_stride = read_u32(&mut reader, is_toc_big_endian)?;
+                        let sample_format_bitmap = read_u8(&mut reader)?;
+                        let scale_id = read_u32(&mut reader, is_toc_big_endian)?;
+
+                        let scaler = DaqFormatChangingScaler {
+                            size: scalers_vector_size,
+                            datatype,
+                            raw_buffer_index,
+                            raw_byte_offset_within_stride,
+                            sample_format_bitmap,
+                            scale_id,
+                        };
+                        scalers.push(scaler);
                     }
                     daqmx_data.scalers = scalers;
 
                     /* vector of raw data width*/
-                    let raw_data_width_vector_size = read_u32(&mut reader, is_toc_big_endian);
+                    let raw_data_width_vector_size = read_u32(&mut reader, is_toc_big_endian)?;
                     daqmx_data.raw_data_size = raw_data_width_vector_size;
 
                     let mut elements = vec![];
                     for _ in 0..raw_data_width_vector_size {
-                        let raw_data_element = read_u32(&mut reader, is_toc_big_endian);
+                        let raw_data_element = read_u32(&mut reader, is_toc_big_endian)?;
                         elements.push(raw_data_element);
                     }
                     daqmx_data.raw_data_vector = elements;
                     raw_data_index = RawDataIndex::Daqmx(daqmx_data);
                 }
-                [0x69, 0x13, 0x00, 0x00] => {
+                0x0000_1369 => {
+                    let mut daqmx_data = DaqmxDigitalLineScaler::new();
                     // DAQmx raw data Digital Line scaler in this segment
-                    unimplemented!()
+                    let datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
+                    if datatype_u32 != 0xffffffff {
+                        return Err(TdmsParseError::IncorrectDataTypeInDaqmxRawDataIndex);
+                    }
+                    daqmx_data.datatype = TdmsDataType::try_from(datatype_u32)?;
+                    daqmx_data.array_dimension = read_u32(&mut reader, is_toc_big_endian)?;
+                    check_array_dimension(daqmx_data.array_dimension, version)?;
+                    daqmx_data.chunk_size = read_u64(&mut reader, is_toc_big_endian)?;
+
+                    /* vector of Digital Line scalers*/
+                    let scalers_vector_size = read_u32(&mut reader, is_toc_big_endian)?;
+                    daqmx_data.scalers_size = scalers_vector_size;
+
+                    let mut scalers: Vec<DaqDigitalLineScaler> = vec![];
+                    for _ in 0..scalers_vector_size {
+                        let datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
+                        let raw_buffer_index = read_u32(&mut reader, is_toc_big_endian)?;
+                        // byte offset within the stride * 8 + bit offset within that byte
+                        let raw_bit_offset = read_u32(&mut reader, is_toc_big_endian)?;
+                        let sample_format_bitmap = read_u8(&mut reader)?;
+                        let scale_id = read_u32(&mut reader, is_toc_big_endian)?;
+                        scalers.push(DaqDigitalLineScaler {
+                            datatype: DaqmxDataType::try_from(datatype_u32)?,
+                            raw_buffer_index,
+                            raw_bit_offset,
+                            sample_format_bitmap,
+                            scale_id,
+                        });
+                    }
+                    daqmx_data.scalers = scalers;
+
+                    /* vector of raw data width*/
+                    let raw_data_width_vector_size = read_u32(&mut reader, is_toc_big_endian)?;
+                    daqmx_data.raw_data_size = raw_data_width_vector_size;
+
+                    let mut elements = vec![];
+                    for _ in 0..raw_data_width_vector_size {
+                        elements.push(read_u32(&mut reader, is_toc_big_endian)?);
+                    }
+                    daqmx_data.raw_data_vector = elements;
+                    raw_data_index = RawDataIndex::DaqmxDigitalLine(daqmx_data);
                 }
-                [0x00, 0x00, 0x00, 0x00] => {
+                0 => {
                     // exactly matches the index the same object had in the previous segment
                     raw_data_index = RawDataIndex::SameAsPrevious;
                 }
-                _ => {
-                    let mut new_raw_data_index_length = 0;
-                    if is_toc_big_endian {
-                        new_raw_data_index_length = BigEndian::read_u32(&raw_data_index_vec);
-                    } else {
-                        new_raw_data_index_length = LittleEndian::read_u32(&raw_data_index_vec);
-                    }
-                    let datatype_u32 = read_u32(&mut reader, is_toc_big_endian);
+                new_raw_data_index_length => {
+                    let datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
                     debug!("datatype = {:?}", datatype_u32);
-                    let datatype: TdmsDataType = datatype_u32.into();
-                    let array_dimension = read_u32(&mut reader, is_toc_big_endian); // TODO: check file version
-                    let number_of_values = read_u64(&mut reader, is_toc_big_endian);
+                    let datatype = TdmsDataType::try_from(datatype_u32)?;
+                    let array_dimension = read_u32(&mut reader, is_toc_big_endian)?;
+                    check_array_dimension(array_dimension, version)?;
+                    let number_of_values = read_u64(&mut reader, is_toc_big_endian)?;
                     debug!("number of values: {}", number_of_values);
 
                     let mut total_size_bytes = 0;
                     if datatype == TdmsDataType::String {
-                        total_size_bytes = read_u64(&mut reader, is_toc_big_endian);
+                        total_size_bytes = read_u64(&mut reader, is_toc_big_endian)?;
                     }
 
                     let new_raw_data_index = NewRawDataIndex {
@@ -145,41 +210,43 @@ impl Metadata {
             }
 
             /* properties */
-            let number_of_properties = read_u32(&mut reader, is_toc_big_endian);
+            let number_of_properties = read_u32(&mut reader, is_toc_big_endian)?;
 
             let mut properties = vec![];
             for _ in 0..number_of_properties {
                 let mut property = TdmsProperty::new();
 
-                let property_name_length = read_u32(&mut reader, is_toc_big_endian);
-                let property_name = read_string(&mut reader, property_name_length as usize)?;
+                let property_name_length = read_u32(&mut reader, is_toc_big_endian)?;
+                let property_name = read_text(
+                    reader,
+                    property_name_length as usize,
+                    strings,
+                    &object_path,
+                )?;
                 property.name = property_name;
 
-                let property_datatype_u32 = read_u32(&mut reader, is_toc_big_endian);
-                let property_datatype: TdmsDataType = property_datatype_u32.into();
+                let property_datatype_u32 = read_u32(&mut reader, is_toc_big_endian)?;
+                let property_datatype = TdmsDataType::try_from(property_datatype_u32)?;
                 property.datatype = property_datatype;
                
                 if property.datatype == TdmsDataType::String {
-                    let property_value_length = read_u32(&mut reader, is_toc_big_endian);
+                    let property_value_length = read_u32(&mut reader, is_toc_big_endian)?;
                     debug!("property_value_length = {:?}", property_value_length);
-                    let property_str_value =
-                        read_string(&mut reader, property_value_length as usize)?;
+                    let property_str_value = read_text(
+                        reader,
+                        property_value_length as usize,
+                        strings,
+                        &object_path,
+                    )?;
                     property.string_value = property_str_value;
-                } else if property.datatype == TdmsDataType::TimeStamp {
-                    let seconds = read_u64(&mut reader, is_toc_big_endian);
-                    debug!("seconds = {:?}", seconds);
-                    let fractions = read_u64(&mut reader, is_toc_big_endian);
-                    debug!("fractions = {:?}", fractions);
-                } else if property.datatype == TdmsDataType::U64 {
-                    let property_value = read_u64(&mut reader, is_toc_big_endian);
-                    property.string_value = property_value.to_string();
-                } else if property.datatype == TdmsDataType::U32 {
-                    let property_value = read_u32(&mut reader, is_toc_big_endian);
-                    property.string_value = property_value.to_string();
-                }
-                else {
-                    let property_value = read_u32(&mut reader, is_toc_big_endian);
+                } else {
+                    // numbers, booleans and timestamps, in the byte order of the file
+                    let property_value =
+                        read_value(&mut reader, &property.datatype, is_toc_big_endian)?;
                     debug!("property_value = {:?}", property_value);
+                    if let Some(value) = property_value {
+                        property.string_value = value.value_string(0);
+                    }
                 }
                 properties.push(property);
             }
@@ -201,3 +268,27 @@ impl Metadata {
         })
     }
 }
+
+/// Reads a string of `size` bytes of the object `path`, invalid UTF-8 is
+/// reported with its file position.
+fn read_text<T: Read>(
+    reader: &mut PositionReader<T>,
+    size: usize,
+    strings: StringDecoding,
+    path: &str,
+) -> Result<String, TdmsParseError> {
+    let pos = reader.pos;
+    let bytes = read_bytes(reader, size)?;
+    decode_string(&bytes, strings).map_err(|valid| TdmsParseError::InvalidUtf8 {
+        path: path.to_string(),
+        offset: pos + valid as u64,
+    })
+}
+
+/// TDMS 1.0 allows arrays of values per chunk, in TDMS 2.0 the dimension is always 1.
+fn check_array_dimension(array_dimension: u32, version: u32) -> Result<(), TdmsParseError> {
+    if version == TDMS_VERSION_2_0 && array_dimension != 1 {
+        return Err(TdmsParseError::IncorrectArrayDimensionInTdmsObject);
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::tdms_datatype::TdmsDataType;
+use crate::tdms_datatype::{DaqmxDataType, TdmsDataType};
 
 #[derive(Debug, Clone)]
 pub struct TdmsObject {
@@ -12,6 +12,7 @@ pub struct TdmsObject {
 pub enum RawDataIndex {
     NoRawData,
     Daqmx(DaqmxScaler),
+    DaqmxDigitalLine(DaqmxDigitalLineScaler),
     SameAsPrevious,
     NewDataIndex(NewRawDataIndex),
 }
@@ -41,13 +42,61 @@ impl DaqmxScaler {
     }
 }
 
+impl Default for DaqmxScaler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DaqFormatChangingScaler {
     pub size: u32,
-    pub datatype: TdmsDataType,
+    pub datatype: DaqmxDataType,
     pub raw_buffer_index: u32,
     pub raw_byte_offset_within_stride: u32,
-    pub sample_format_bitmap: u32,
+    pub sample_format_bitmap: u8,
+    pub scale_id: u32,
+}
+
+/// DAQmx raw data index with Digital Line scalers (`69 13 00 00`).
+#[derive(Debug, Clone)]
+pub struct DaqmxDigitalLineScaler {
+    pub datatype: TdmsDataType,
+    pub array_dimension: u32,
+    pub chunk_size: u64,
+    pub scalers_size: u32,
+    pub scalers: std::vec::Vec<DaqDigitalLineScaler>,
+    pub raw_data_size: u32,
+    pub raw_data_vector: std::vec::Vec<u32>,
+}
+
+impl DaqmxDigitalLineScaler {
+    pub fn new() -> Self {
+        DaqmxDigitalLineScaler {
+            datatype: TdmsDataType::Boolean,
+            array_dimension: 0,
+            chunk_size: 0,
+            scalers_size: 0,
+            scalers: std::vec::Vec::new(),
+            raw_data_size: 0,
+            raw_data_vector: Vec::new(),
+        }
+    }
+}
+
+impl Default for DaqmxDigitalLineScaler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DaqDigitalLineScaler {
+    pub datatype: DaqmxDataType,
+    pub raw_buffer_index: u32,
+    /// Position of the line in the raw buffer stride, in bits.
+    pub raw_bit_offset: u32,
+    pub sample_format_bitmap: u8,
     pub scale_id: u32,
 }
 
@@ -77,3 +126,9 @@ impl TdmsProperty {
         }
     }
 }
+
+impl Default for TdmsProperty {
+    fn default() -> Self {
+        Self::new()
+    }
+}
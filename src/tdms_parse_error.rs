@@ -1,18 +1,26 @@
-#![allow(dead_code)]
-
 use log::SetLoggerError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum TdmsParseError {
-    #[error("IO error.")]
+    #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("SetLoggerError.")]
     SetLoggerError(#[from] SetLoggerError),
     #[error("tdms tag incorrect")]
     IncorrectTdmsTag,
+    #[error("Unsupported TDMS file format version {version}")]
+    UnsupportedTdmsVersion { version: u32 },
+    #[error("Invalid segment offsets: next segment {next_segment_offset}, raw data {raw_data_offset}")]
+    InvalidSegmentOffsets { next_segment_offset: u64, raw_data_offset: u64 },
     #[error("In TDMS file format version 2.0, 1 is the only valid value")]
     IncorrectArrayDimensionInTdmsObject,
+    #[error("Unknown data type {code:#x}")]
+    UnknownDataType { code: u32 },
+    #[error("Unknown DAQmx data type {code:#x}")]
+    UnknownDaqmxDataType { code: u32 },
+    #[error("Property {property} has the invalid value {value}")]
+    InvalidPropertyValue { property: String, value: String },
     #[error("Incorrect datatype in the DAQmx raw data index.")]
     IncorrectDataTypeInDaqmxRawDataIndex,
     #[error("Cannot read timestamp channel.")]
@@ -25,6 +33,8 @@ pub enum TdmsParseError {
     CannotReadMetadataChannel,
     #[error("Unexpected segment")]
     UnexpectedSegment,
+    #[error("Invalid UTF-8 in {path} at offset {offset}")]
+    InvalidUtf8 { path: String, offset: u64 },
     #[error("Unexpected group")]
     UnexpectedGroup,
     #[error("Unexpected channel")]
@@ -33,8 +43,82 @@ pub enum TdmsParseError {
     UnexpectedProperty { property: String },
     #[error("Contain no metadata")]
     NoMetadata,
+    #[error("Object {path} has no raw data index in a previous segment")]
+    NoPreviousRawDataIndex { path: String },
+    #[error("Cannot decode the raw data of {path}: {message}")]
+    UnsupportedRawData { path: String, message: String },
     #[error("index {index}, timestamp {timestamp} or header {header} channel length mismatch")]
     ChannelLengthMismatch { index: usize, timestamp: usize, header: usize },
+    #[error("The split chain has {issues} continuity issues")]
+    BrokenSplitChain { issues: usize },
+    #[error("Cannot write output file {path}: {message}")]
+    OutputError { path: String, message: String },
     #[error("Error occurred: {message}")]
     GeneralError { message: String },
 }
+
+pub const EXIT_SUCCESS: i32 = 0;
+
+/// Error categories, each mapped to a distinct process exit code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCategory {
+    General = 1,
+    Io = 2,
+    NotTdmsFile = 3,
+    UnsupportedStructure = 4,
+    InconsistentChannels = 5,
+    Output = 6,
+    InconsistentRecording = 7,
+}
+
+impl ErrorCategory {
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCategory::General => "general",
+            ErrorCategory::Io => "io",
+            ErrorCategory::NotTdmsFile => "not_tdms_file",
+            ErrorCategory::UnsupportedStructure => "unsupported_structure",
+            ErrorCategory::InconsistentChannels => "inconsistent_channels",
+            ErrorCategory::Output => "output",
+            ErrorCategory::InconsistentRecording => "inconsistent_recording",
+        }
+    }
+}
+
+impl TdmsParseError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            TdmsParseError::IoError(_) => ErrorCategory::Io,
+            TdmsParseError::IncorrectTdmsTag => ErrorCategory::NotTdmsFile,
+            TdmsParseError::UnsupportedTdmsVersion { .. }
+            | TdmsParseError::InvalidSegmentOffsets { .. }
+            | TdmsParseError::IncorrectArrayDimensionInTdmsObject
+            | TdmsParseError::UnknownDataType { .. }
+            | TdmsParseError::UnknownDaqmxDataType { .. }
+            | TdmsParseError::InvalidPropertyValue { .. }
+            | TdmsParseError::IncorrectDataTypeInDaqmxRawDataIndex
+            | TdmsParseError::CannotReadTimestampChannel
+            | TdmsParseError::CannotReadIndexChannel
+            | TdmsParseError::CannotReadHeaderChannel
+            | TdmsParseError::CannotReadMetadataChannel
+            | TdmsParseError::UnexpectedSegment
+            | TdmsParseError::UnexpectedGroup
+            | TdmsParseError::UnexpectedChannel
+            | TdmsParseError::UnexpectedProperty { .. }
+            | TdmsParseError::NoMetadata
+            | TdmsParseError::InvalidUtf8 { .. }
+            | TdmsParseError::NoPreviousRawDataIndex { .. }
+            | TdmsParseError::UnsupportedRawData { .. } => ErrorCategory::UnsupportedStructure,
+            TdmsParseError::ChannelLengthMismatch { .. } => ErrorCategory::InconsistentChannels,
+            TdmsParseError::OutputError { .. } => ErrorCategory::Output,
+            TdmsParseError::BrokenSplitChain { .. } => ErrorCategory::InconsistentRecording,
+            TdmsParseError::SetLoggerError(_) | TdmsParseError::GeneralError { .. } => {
+                ErrorCategory::General
+            }
+        }
+    }
+}
@@ -1,10 +1,248 @@
-use crate::tdms_lead_in;
-use crate::tdms_metadata;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::tdms_datatype::TdmsDataType;
+use crate::tdms_lead_in::{has_lead_in_at, LeadIn, LEAD_IN_SIZE};
+use crate::tdms_metadata::Metadata;
+use crate::tdms_object::{RawDataIndex, TdmsObject};
+use crate::tdms_parse_error::TdmsParseError;
+use crate::util::StringDecoding;
+
+/// `next_segment_offset` value written by a logger that was interrupted
+/// before it could finalize the segment.
+pub const INCOMPLETE_SEGMENT_OFFSET: u64 = 0xffff_ffff_ffff_ffff;
 
 #[derive(Debug)]
 pub struct TdmsSegment {
-    pub tdms_lead_in: TdmsLeadIn,
-    pub tdms_metadata: TdmsMetadata,
-    pub data_size: u64,
     pub absolute_pos: u64,
+    pub lead_in: LeadIn,
+    pub metadata: Option<Metadata>,
+}
+
+impl TdmsSegment {
+    /// Reads the lead-in and (if present) the metadata of the segment starting at `absolute_pos`.
+    pub fn read<T: Read + Seek>(
+        reader: &mut T,
+        absolute_pos: u64,
+    ) -> Result<TdmsSegment, TdmsParseError> {
+        Self::read_with(reader, absolute_pos, StringDecoding::Strict)
+    }
+
+    /// Like [`TdmsSegment::read`], decoding the strings of the metadata with `strings`.
+    pub fn read_with<T: Read + Seek>(
+        mut reader: &mut T,
+        absolute_pos: u64,
+        strings: StringDecoding,
+    ) -> Result<TdmsSegment, TdmsParseError> {
+        reader.seek(SeekFrom::Start(absolute_pos))?;
+        let lead_in = LeadIn::read(&mut reader)?;
+        let metadata = if lead_in.contains_metadata {
+            Some(Metadata::read_with(
+                &mut reader,
+                &lead_in,
+                absolute_pos + LEAD_IN_SIZE,
+                strings,
+            )?)
+        } else {
+            None
+        };
+
+        Ok(TdmsSegment {
+            absolute_pos,
+            lead_in,
+            metadata,
+        })
+    }
+
+    /// Absolute file position of the following segment, `None` for an unfinished segment.
+    pub fn next_segment_pos(&self) -> Result<Option<u64>, TdmsParseError> {
+        self.lead_in.next_segment_pos(self.absolute_pos)
+    }
+}
+
+/// Reads consecutive segments of a file and keeps the object state that TDMS
+/// carries over from one segment to the next.
+///
+/// The metadata returned by [`SegmentReader::read_header`] is resolved: it lists
+/// the objects of the segment's object list in raw data order, a "same as
+/// previous" raw data index is replaced by the object's last index and the
+/// properties include those written in earlier segments. A segment without
+/// metadata repeats the object list of the previous segment.
+#[derive(Debug, Default, Clone)]
+pub struct SegmentReader {
+    /// Paths of the objects in the current object list, in raw data order.
+    object_list: Vec<String>,
+    /// Last raw data index and accumulated properties of every object seen so far.
+    objects: HashMap<String, TdmsObject>,
+    /// Data type of the last raw data index of every object, kept when a later
+    /// segment has no raw data for the object.
+    datatypes: HashMap<String, TdmsDataType>,
+    strings: StringDecoding,
+}
+
+impl SegmentReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A reader decoding the strings of the metadata with `strings`.
+    pub fn with_string_decoding(strings: StringDecoding) -> Self {
+        SegmentReader {
+            strings,
+            ..Self::default()
+        }
+    }
+
+    /// Data type of the raw data last written for the object at `path`.
+    pub fn datatype(&self, path: &str) -> Option<&TdmsDataType> {
+        self.datatypes.get(path)
+    }
+
+    /// Reads the lead-in and (if present) the metadata of the segment at the reader's position.
+    pub fn read_header<T: Read + Seek>(
+        &mut self,
+        mut reader: &mut T,
+    ) -> Result<(LeadIn, Metadata), TdmsParseError> {
+        let lead_in = LeadIn::read(&mut reader)?;
+        debug!("{:?}", lead_in);
+        let metadata = if lead_in.contains_metadata {
+            let metadata_pos = reader.stream_position()?;
+            Some(Metadata::read_with(
+                &mut reader,
+                &lead_in,
+                metadata_pos,
+                self.strings,
+            )?)
+        } else {
+            None
+        };
+        let metadata = self.resolve(&lead_in, metadata.as_ref())?;
+        debug!("{:?}", metadata);
+        Ok((lead_in, metadata))
+    }
+
+    /// Applies the metadata of a segment to the object state and returns the resolved metadata.
+    pub fn resolve(
+        &mut self,
+        lead_in: &LeadIn,
+        metadata: Option<&Metadata>,
+    ) -> Result<Metadata, TdmsParseError> {
+        if let Some(metadata) = metadata {
+            if lead_in.contains_new_object_list {
+                self.object_list.clear();
+            }
+            for object in metadata.objects.iter() {
+                self.update(object)?;
+            }
+        } else if lead_in.contains_raw_data && self.object_list.is_empty() {
+            return Err(TdmsParseError::NoMetadata);
+        }
+
+        let objects: Vec<TdmsObject> = self
+            .object_list
+            .iter()
+            .map(|path| self.objects[path].clone())
+            .collect();
+        Ok(Metadata {
+            object_number: objects.len() as u32,
+            objects,
+        })
+    }
+
+    fn update(&mut self, object: &TdmsObject) -> Result<(), TdmsParseError> {
+        if !self.object_list.contains(&object.path) {
+            self.object_list.push(object.path.clone());
+        }
+
+        let raw_data_index = match &object.raw_data_index {
+            RawDataIndex::SameAsPrevious => match self.objects.get(&object.path) {
+                Some(previous) => previous.raw_data_index.clone(),
+                None => {
+                    return Err(TdmsParseError::NoPreviousRawDataIndex {
+                        path: object.path.clone(),
+                    })
+                }
+            },
+            raw_data_index => raw_data_index.clone(),
+        };
+        if let RawDataIndex::NewDataIndex(index) = &raw_data_index {
+            self.datatypes
+                .insert(object.path.clone(), index.datatype.clone());
+        }
+
+        let state = self
+            .objects
+            .entry(object.path.clone())
+            .or_insert_with(|| TdmsObject {
+                path: object.path.clone(),
+                raw_data_index: RawDataIndex::NoRawData,
+                property_size: 0,
+                properties: vec![],
+            });
+        state.raw_data_index = raw_data_index;
+        for property in object.properties.iter() {
+            match state
+                .properties
+                .iter_mut()
+                .find(|p| p.name == property.name)
+            {
+                Some(existing) => *existing = property.clone(),
+                None => state.properties.push(property.clone()),
+            }
+        }
+        state.property_size = state.properties.len() as u32;
+        Ok(())
+    }
+}
+
+/// Walks the segment chain from the beginning of the file until its end.
+pub fn read_segments<T: Read + Seek>(reader: &mut T) -> Result<Vec<TdmsSegment>, TdmsParseError> {
+    read_segments_with(reader, StringDecoding::Strict)
+}
+
+/// Like [`read_segments`], decoding the strings of the metadata with `strings`.
+pub fn read_segments_with<T: Read + Seek>(
+    mut reader: &mut T,
+    strings: StringDecoding,
+) -> Result<Vec<TdmsSegment>, TdmsParseError> {
+    let file_length = reader.seek(SeekFrom::End(0))?;
+
+    let mut segments = vec![];
+    let mut pos = 0;
+    while pos < file_length {
+        if !has_lead_in_at(pos, file_length) {
+            warn!("truncated lead-in at byte offset {}", pos);
+            break;
+        }
+        let segment = TdmsSegment::read_with(&mut reader, pos, strings)?;
+        let next_segment_pos = segment.next_segment_pos()?;
+        segments.push(segment);
+        match next_segment_pos {
+            Some(next_pos) => pos = next_pos,
+            None => break,
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Checks whether the logger has finished writing the file, i.e. every lead-in
+/// is valid and the last segment ends exactly at the end of the file.
+pub fn is_complete<T: Read + Seek>(mut reader: &mut T) -> Result<bool, TdmsParseError> {
+    let file_length = reader.seek(SeekFrom::End(0))?;
+
+    let mut pos = 0;
+    while has_lead_in_at(pos, file_length) {
+        reader.seek(SeekFrom::Start(pos))?;
+        let lead_in = LeadIn::read(&mut reader)?;
+        pos = match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) => next_pos,
+            None => return Ok(false),
+        };
+        if pos == file_length {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
 }
@@ -0,0 +1,31 @@
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+
+/// The two high bits of a unified hardware timestamp are flags whose meaning
+/// is not documented, the remaining 62 bits hold the nanoseconds since the
+/// last clock reset.
+pub const QUALITY_SHIFT: u32 = 62;
+
+const VALUE_MASK: u64 = (1 << QUALITY_SHIFT) - 1;
+
+/// Splits a raw unified hardware timestamp into nanoseconds and the raw value
+/// of its two high bits.
+pub fn split_unified_timestamp(raw: u64) -> (u64, u8) {
+    (raw & VALUE_MASK, (raw >> QUALITY_SHIFT) as u8)
+}
+
+/// Nanoseconds since the Unix epoch of a relative timestamp.
+///
+/// `FutureTimeEvent` is the UTC time in nanoseconds since the epoch at which
+/// the relative timestamps were reset to 0. `None` if the time is beyond the
+/// year 2262, the limit of nanoseconds since the epoch in an `i64`.
+pub fn epoch_nanos(future_time_event: u64, relative: u64) -> Option<u64> {
+    future_time_event
+        .checked_add(relative)
+        .filter(|&nanos| nanos <= i64::MAX as u64)
+}
+
+/// ISO 8601 representation of nanoseconds since the Unix epoch.
+pub fn to_iso8601(epoch_nanos: u64) -> String {
+    let time: DateTime<Utc> = Utc.timestamp_nanos(epoch_nanos as i64);
+    time.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
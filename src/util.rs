@@ -1,51 +1,112 @@
-use byteorder::{BigEndian, ByteOrder, LittleEndian, NativeEndian};
-use std::any::TypeId;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 
-use crate::TdmsParseError;
+use crate::tdms_parse_error::TdmsParseError;
 
-pub fn is_little_endian() -> bool {
-    TypeId::of::<NativeEndian>() == TypeId::of::<LittleEndian>()
+pub fn load_part<T: Read>(reader: &mut T, size: usize) -> Result<Vec<u8>, TdmsParseError> {
+    let mut buf = vec![0; size];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
 }
 
-pub fn load_part<T: Read>(reader: &mut T, size: usize) -> std::vec::Vec<u8> {
-    let mut buf = Vec::with_capacity(size);
-    let mut part_reader = reader.take(size as u64);
-    part_reader.read_to_end(&mut buf).unwrap();
-    buf
+pub fn read_u8<T: Read>(reader: &mut T) -> Result<u8, TdmsParseError> {
+    Ok(load_part(reader, 1)?[0])
 }
 
-pub fn read_u32<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> u32 {
-    match is_toc_big_endian {
-        true => BigEndian::read_u32(&mut load_part(reader, 4)),
-        false => LittleEndian::read_u32(&mut load_part(reader, 4)),
-    }
+pub fn read_u32<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> Result<u32, TdmsParseError> {
+    let bytes = load_part(reader, 4)?;
+    Ok(match is_toc_big_endian {
+        true => BigEndian::read_u32(&bytes),
+        false => LittleEndian::read_u32(&bytes),
+    })
 }
 
-pub fn read_u64<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> u64 {
-    match is_toc_big_endian {
-        true => BigEndian::read_u64(&mut load_part(reader, 8)),
-        false => LittleEndian::read_u64(&mut load_part(reader, 8)),
-    }
+pub fn read_u64<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> Result<u64, TdmsParseError> {
+    let bytes = load_part(reader, 8)?;
+    Ok(match is_toc_big_endian {
+        true => BigEndian::read_u64(&bytes),
+        false => LittleEndian::read_u64(&bytes),
+    })
 }
 
-pub fn read_i64<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> i64 {
-    match is_toc_big_endian {
-        true => BigEndian::read_i64(&mut load_part(reader, 8)),
-        false => LittleEndian::read_i64(&mut load_part(reader, 8)),
-    }
+pub fn read_i64<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> Result<i64, TdmsParseError> {
+    let bytes = load_part(reader, 8)?;
+    Ok(match is_toc_big_endian {
+        true => BigEndian::read_i64(&bytes),
+        false => LittleEndian::read_i64(&bytes),
+    })
 }
 
-pub fn read_string<T: Read>(reader: &mut T, size: usize) -> Result<String, TdmsParseError> {
+pub fn read_bytes<T: Read>(reader: &mut T, size: usize) -> Result<Vec<u8>, TdmsParseError> {
     let mut buffer = vec![0; size];
-    reader.read(&mut buffer)?;
-    let s = buffer.iter().map(|&c| c as char).collect::<String>();
-    Ok(s)
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// How strings that are not valid UTF-8 are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringDecoding {
+    /// Invalid UTF-8 is an error.
+    #[default]
+    Strict,
+    /// Invalid sequences are replaced by U+FFFD.
+    Lossy,
 }
 
-pub fn read_unified_timestamp<T: Read>(reader: &mut T, is_toc_big_endian: bool) -> u64 {
-    let mut value = read_u64(reader, is_toc_big_endian);
-    value &= !(1u64 << 63);
-    value &= !(1u64 << 62);
-    value
+/// Decodes UTF-8 text, the error holds the position of the first invalid byte.
+pub fn decode_string(bytes: &[u8], strings: StringDecoding) -> Result<String, usize> {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) if strings == StringDecoding::Lossy => {
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        }
+        Err(e) => Err(e.valid_up_to()),
+    }
+}
+
+/// Writes `value` as JSON through a temporary file so an interrupted write
+/// never leaves a corrupted file behind.
+pub fn write_json_atomically<T: Serialize>(path: &Path, value: &T) -> Result<(), TdmsParseError> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let output_error = |message: String| TdmsParseError::OutputError {
+        path: path.to_string_lossy().to_string(),
+        message,
+    };
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temp_path)
+        .map_err(|e| output_error(e.to_string()))?;
+    serde_json::to_writer_pretty(&file, value).map_err(|e| output_error(e.to_string()))?;
+    file.sync_all().map_err(|e| output_error(e.to_string()))?;
+    fs::rename(&temp_path, path).map_err(|e| output_error(e.to_string()))?;
+    Ok(())
+}
+
+/// Counts the bytes read to know the absolute file position of a reader
+/// without requiring `Seek`.
+pub struct PositionReader<R> {
+    inner: R,
+    pub pos: u64,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub fn new(inner: R, pos: u64) -> Self {
+        PositionReader { inner, pos }
+    }
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
 }
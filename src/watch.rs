@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::batch::collect_directory;
+use crate::tdms_parse_error::TdmsParseError;
+use crate::tdms_segment::is_complete;
+use crate::util::write_json_atomically;
+
+/// Persistent record of the files the watcher has already indexed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    #[serde(rename = "Indexed")]
+    pub indexed: BTreeMap<String, IndexedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedFile {
+    #[serde(rename = "Size")]
+    pub size: u64,
+    #[serde(rename = "IndexedAt")]
+    pub indexed_at: String,
+}
+
+impl WatchState {
+    /// Loads the state file, a missing file yields an empty state.
+    pub fn load(path: &Path) -> Result<WatchState, TdmsParseError> {
+        if !path.exists() {
+            return Ok(WatchState::default());
+        }
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| TdmsParseError::GeneralError {
+            message: format!("invalid watch state file {}: {}", path.display(), e),
+        })
+    }
+
+    /// Writes the state through a temporary file so an interrupted write never
+    /// leaves a corrupted state behind.
+    pub fn save(&self, path: &Path) -> Result<(), TdmsParseError> {
+        write_json_atomically(path, self)
+    }
+}
+
+#[derive(Debug)]
+pub struct WatchOptions {
+    pub dirs: Vec<PathBuf>,
+    pub state_file: PathBuf,
+    /// How long the file size has to stay unchanged before a file is considered closed.
+    pub settle_time: Duration,
+    pub poll_interval: Duration,
+}
+
+#[derive(Debug)]
+struct PendingFile {
+    size: u64,
+    since: Instant,
+}
+
+pub struct Watcher {
+    options: WatchOptions,
+    state: WatchState,
+    pending: HashMap<PathBuf, PendingFile>,
+    // size at which indexing failed, the file is retried once it changes
+    failed: HashMap<PathBuf, u64>,
+    // the state has changes that could not be saved yet
+    unsaved: bool,
+}
+
+impl Watcher {
+    pub fn new(mut options: WatchOptions) -> Result<Watcher, TdmsParseError> {
+        // absolute paths keep the state file valid independent of the working directory
+        options.dirs = options
+            .dirs
+            .iter()
+            .map(fs::canonicalize)
+            .collect::<Result<Vec<_>, _>>()?;
+        let state = WatchState::load(&options.state_file)?;
+        info!(
+            "{} files already indexed according to {}",
+            state.indexed.len(),
+            options.state_file.display()
+        );
+        Ok(Watcher {
+            options,
+            state,
+            pending: HashMap::new(),
+            failed: HashMap::new(),
+            unsaved: false,
+        })
+    }
+
+    /// Polls the watched directories forever.
+    pub fn run<F>(&mut self, index: F) -> Result<(), TdmsParseError>
+    where
+        F: Fn(&Path) -> Result<(), TdmsParseError>,
+    {
+        loop {
+            self.poll(&index);
+            thread::sleep(self.options.poll_interval);
+        }
+    }
+
+    /// Scans the watched directories once and indexes every file that has been
+    /// completed since the last scan. Returns the number of indexed files.
+    ///
+    /// Errors are logged: a directory that cannot be scanned and a state file
+    /// that cannot be written are retried on the next poll.
+    pub fn poll<F>(&mut self, index: F) -> usize
+    where
+        F: Fn(&Path) -> Result<(), TdmsParseError>,
+    {
+        if self.unsaved {
+            self.save_state();
+        }
+        let mut files = vec![];
+        for dir in self.options.dirs.iter() {
+            if let Err(e) = collect_directory(dir, &mut files) {
+                error!("cannot scan {}: {}", dir.display(), e);
+            }
+        }
+
+        let mut indexed = 0;
+        for path in files {
+            let size = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue, // removed in the meantime
+            };
+            if !self.is_settled(&path, size) {
+                continue;
+            }
+
+            let complete = File::open(&path)
+                .map_err(TdmsParseError::from)
+                .and_then(|file| is_complete(&mut BufReader::new(file)));
+            match complete {
+                Ok(true) => {}
+                Ok(false) => {
+                    debug!("{} is not complete yet", path.display());
+                    continue;
+                }
+                Err(e) => {
+                    error!("{}: {:?}", path.display(), e);
+                    self.pending.remove(&path);
+                    self.failed.insert(path, size);
+                    continue;
+                }
+            }
+
+            self.pending.remove(&path);
+            info!("indexing {}", path.display());
+            match index(&path) {
+                Ok(()) => {
+                    self.state.indexed.insert(
+                        path.to_string_lossy().to_string(),
+                        IndexedFile {
+                            size,
+                            indexed_at: Utc::now().to_rfc3339(),
+                        },
+                    );
+                    self.save_state();
+                    indexed += 1;
+                }
+                Err(e) => {
+                    error!("{}: {:?}", path.display(), e);
+                    self.failed.insert(path, size);
+                }
+            }
+        }
+
+        indexed
+    }
+
+    /// Saves the state, a failure is logged and the state saved again on the next poll.
+    fn save_state(&mut self) {
+        self.unsaved = match self.state.save(&self.options.state_file) {
+            Ok(()) => false,
+            Err(e) => {
+                error!("{}", e);
+                true
+            }
+        };
+    }
+
+    /// Tracks the size of a not yet indexed file, returns `true` once it has not
+    /// grown for the settle time.
+    fn is_settled(&mut self, path: &Path, size: u64) -> bool {
+        let key = path.to_string_lossy();
+        if let Some(indexed) = self.state.indexed.get(key.as_ref()) {
+            if indexed.size == size {
+                return false;
+            }
+        }
+        if self.failed.get(path) == Some(&size) {
+            return false;
+        }
+
+        match self.pending.get(path) {
+            Some(pending) if pending.size == size => {
+                pending.since.elapsed() >= self.options.settle_time
+            }
+            _ => {
+                self.pending.insert(
+                    path.to_path_buf(),
+                    PendingFile {
+                        size,
+                        since: Instant::now(),
+                    },
+                );
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::hvds::{index_file, IndexOptions};
+
+use common::{hvds_segments, Object, Property, TdmsWriter};
+
+fn index_with_future_time_event(name: &str, future_time_event: u64) -> adas_hvds::output::Data {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 2, false);
+    writer.segment(
+        &[Object::new("/").property("FutureTimeEvent", Property::U64(future_time_event))],
+        false,
+    );
+    let path = writer.write(name);
+
+    let options = IndexOptions {
+        absolute_time: true,
+        ..IndexOptions::default()
+    };
+    let output = index_file(&path, &options).unwrap();
+    fs::remove_file(path.with_extension("hvds.json")).unwrap();
+    fs::remove_file(path).unwrap();
+    output.file.stream.data
+}
+
+#[test]
+fn adds_the_future_time_event_to_the_relative_timestamps() {
+    let data = index_with_future_time_event("absolute_time.tdms", 1_600_000_000_000_000_000);
+
+    assert_eq!(
+        data.absolute_timestamp,
+        vec![1_600_000_000_000_001_000, 1_600_000_000_000_001_100]
+    );
+    assert_eq!(data.absolute_time[1], "2020-09-13T12:26:40.000001100Z");
+    assert_eq!(data.timestamp_quality, vec![0, 0]);
+}
+
+#[test]
+fn omits_absolute_times_out_of_range() {
+    let data = index_with_future_time_event("absolute_time_overflow.tdms", u64::MAX - 1_050);
+
+    assert!(data.absolute_timestamp.is_empty());
+    assert!(data.absolute_time.is_empty());
+    assert_eq!(data.timestamp, vec![1_000, 1_100]);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use adas_hvds::hvds::{index, IndexOptions};
+use adas_hvds::incremental::index_incremental;
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+use common::{hvds_segments, Object, TdmsWriter, Values};
+
+fn strings(values: &[&str]) -> Values {
+    Values::String(values.iter().map(|value| value.to_string()).collect())
+}
+
+fn remove_outputs(path: &Path) {
+    fs::remove_file(path.with_extension("hvds.json")).unwrap();
+    fs::remove_file(path.with_extension("hvds.state.json")).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn exposes_string_channels_per_frame() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 3, false);
+    writer
+        .segment(
+            &[Object::channel(
+                "/'Stream'/'Annotation'",
+                strings(&["start", "", "lane change"]),
+            )],
+            false,
+        )
+        .segment(
+            &[Object::channel("/'Stream'/'Driver'", strings(&["A", "B"]))],
+            false,
+        );
+    let path = writer.write("annotations.tdms");
+
+    let output = index(path.to_str().unwrap()).unwrap();
+    let annotations = &output.file.stream.data.annotations;
+    assert_eq!(annotations["Annotation"], vec!["start", "", "lane change"]);
+    // a channel with fewer values than frames is padded
+    assert_eq!(annotations["Driver"], vec!["A", "B", ""]);
+
+    let json = serde_json::to_value(&output).unwrap();
+    assert_eq!(
+        json["File"]["Stream"]["Data"]["Annotations"]["Annotation"][2],
+        "lane change"
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn omits_annotations_without_string_channels() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 2, false);
+    let path = writer.write("no_annotations.tdms");
+
+    let output = index(path.to_str().unwrap()).unwrap();
+    assert!(output.file.stream.data.annotations.is_empty());
+    let json = serde_json::to_value(&output).unwrap();
+    assert!(json["File"]["Stream"]["Data"].get("Annotations").is_none());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn fills_in_annotations_written_after_their_frames() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 3, false);
+    writer.segment(
+        &[Object::channel(
+            "/'Stream'/'Annotation'",
+            strings(&["a", "b"]),
+        )],
+        false,
+    );
+    let path = writer.write("incremental_annotations.tdms");
+
+    let options = IndexOptions::default();
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(
+        output.file.stream.data.annotations["Annotation"],
+        vec!["a", "b", ""]
+    );
+
+    writer.segment(
+        &[Object::channel("/'Stream'/'Annotation'", strings(&["c"]))],
+        false,
+    );
+    fs::write(&path, &writer.bytes).unwrap();
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 0);
+    assert_eq!(
+        output.file.stream.data.annotations["Annotation"],
+        vec!["a", "b", "c"]
+    );
+    assert_eq!(output.file.stream.data.frame_number, vec![1, 2, 3]);
+    remove_outputs(&path);
+}
+
+#[test]
+fn incremental_annotations_match_the_full_index() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 3, false);
+    writer.segment(
+        &[Object::channel("/'Stream'/'Driver'", strings(&["A"]))],
+        false,
+    );
+    let path = writer.write("incremental_full_annotations.tdms");
+    let options = IndexOptions::default();
+    let (_, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 3);
+
+    writer
+        .segment(
+            &[Object::channel("/'Stream'/'Driver'", strings(&["B"]))],
+            false,
+        )
+        .segment(
+            &[Object::channel("/'Stream'/'Annotation'", strings(&["x"]))],
+            false,
+        );
+    fs::write(&path, &writer.bytes).unwrap();
+    let (incremental, _) = index_incremental(&path, &options).unwrap();
+    let full = index(path.to_str().unwrap()).unwrap();
+
+    let annotations = &incremental.file.stream.data.annotations;
+    assert_eq!(annotations, &full.file.stream.data.annotations);
+    assert_eq!(annotations["Driver"], vec!["A", "B", ""]);
+    assert_eq!(annotations["Annotation"], vec!["x", "", ""]);
+    assert_eq!(
+        incremental.file.stream.data.frame_number,
+        full.file.stream.data.frame_number
+    );
+    remove_outputs(&path);
+}
+
+#[test]
+fn accepts_annotation_channel_without_raw_data_in_a_segment() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 2, false);
+    writer
+        .segment(
+            &[Object::channel(
+                "/'Stream'/'Annotation'",
+                strings(&["a", "b"]),
+            )],
+            false,
+        )
+        .segment(
+            &[Object::new("/'Stream'/'Annotation'").string("Author", "driver")],
+            false,
+        );
+    let path = writer.write("annotations_no_raw_data.tdms");
+
+    let output = index(path.to_str().unwrap()).unwrap();
+    assert_eq!(
+        output.file.stream.data.annotations["Annotation"],
+        vec!["a", "b"]
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_string_channels_outside_the_stream() {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, 2, false);
+    writer.segment(
+        &[Object::channel("/'Other'/'Note'", strings(&["a", "b"]))],
+        false,
+    );
+    let path = writer.write("annotations_other_group.tdms");
+
+    match index(path.to_str().unwrap()) {
+        Err(TdmsParseError::UnexpectedChannel) => {}
+        other => panic!("expected an unexpected channel, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use adas_hvds::batch::{collect_inputs, run, write_summary, BatchResult};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+use common::temp_path;
+
+/// A fresh directory with the given (empty) files.
+fn directory(name: &str, files: &[&str]) -> PathBuf {
+    let dir = temp_path(name);
+    let _ = fs::remove_dir_all(&dir);
+    for file in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+    dir
+}
+
+fn failure(message: &str) -> TdmsParseError {
+    TdmsParseError::GeneralError {
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn expands_directories_recursively() {
+    let dir = directory(
+        "batch_directory",
+        &[
+            "b.tdms",
+            "a.TDMS",
+            "notes.txt",
+            "a.tdms_index",
+            "sub/c.tdms",
+        ],
+    );
+
+    let files = collect_inputs(&[dir.to_str().unwrap()]).unwrap();
+    let expected: Vec<PathBuf> = ["a.TDMS", "b.tdms", "sub/c.tdms"]
+        .iter()
+        .map(|file| dir.join(file))
+        .collect();
+    assert_eq!(files, expected);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn expands_patterns_and_drops_duplicates() {
+    let dir = directory("batch_pattern", &["a.tdms", "b.tdms", "c.txt"]);
+    let pattern = dir.join("*.tdms");
+    let file = dir.join("b.tdms");
+    let missing = dir.join("missing.tdms");
+
+    let files = collect_inputs(&[
+        file.to_str().unwrap(),
+        pattern.to_str().unwrap(),
+        dir.to_str().unwrap(),
+        missing.to_str().unwrap(),
+    ])
+    .unwrap();
+    // files are taken as given, even if they do not exist
+    assert_eq!(files, vec![file, dir.join("a.tdms"), missing]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn invalid_pattern_is_an_error() {
+    assert!(matches!(
+        collect_inputs(&["[.tdms"]),
+        Err(TdmsParseError::GeneralError { .. })
+    ));
+}
+
+#[test]
+fn records_the_result_of_every_file_in_order() {
+    let files: Vec<PathBuf> = (0..8)
+        .map(|i| PathBuf::from(format!("{}.tdms", i)))
+        .collect();
+
+    let results = run(&files, 3, |path: &Path| match path.to_str().unwrap() {
+        "2.tdms" => Err(failure("broken")),
+        "5.tdms" => panic!("unexpected data"),
+        _ => Ok(()),
+    });
+
+    let paths: Vec<&PathBuf> = results.iter().map(|result| &result.path).collect();
+    assert_eq!(paths, files.iter().collect::<Vec<_>>());
+    let failed: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.error.is_some())
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(failed, vec![2, 5]);
+    match &results[5].error {
+        Some(TdmsParseError::GeneralError { message }) => {
+            assert_eq!(message, "panicked: unexpected data")
+        }
+        other => panic!("expected the panic as error, got {:?}", other),
+    }
+}
+
+#[test]
+fn summarizes_successes_and_failures() {
+    let results = vec![
+        BatchResult {
+            path: PathBuf::from("a.tdms"),
+            duration: Duration::from_millis(1_500),
+            error: None,
+        },
+        BatchResult {
+            path: PathBuf::from("b.tdms"),
+            duration: Duration::from_millis(250),
+            error: Some(failure("broken")),
+        },
+    ];
+
+    let mut out = vec![];
+    write_summary(&mut out, &results).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "OK        1.500s  a.tdms\n\
+         FAIL      0.250s  b.tdms: Error occurred: broken\n\
+         1 succeeded, 1 failed, 1.750s total indexing time\n"
+    );
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::BufReader;
+
+use adas_hvds::channel_decoder::{read_channels, ChannelData, TdmsTimestamp};
+use adas_hvds::hvds::index;
+use adas_hvds::tdms_lead_in::LeadIn;
+use adas_hvds::tdms_parse_error::TdmsParseError;
+use adas_hvds::tdms_segment::read_segments;
+
+use common::{Object, Property, TdmsWriter, Values};
+
+#[test]
+fn indexes_big_endian_hvds_like_little_endian() {
+    for interleaved in [false, true] {
+        let little = common::hvds_file(&format!("le_{}", interleaved), 5, false, interleaved);
+        let big = common::hvds_file(&format!("be_{}", interleaved), 5, true, interleaved);
+        let expected = index(little.to_str().unwrap()).unwrap();
+        let output = index(big.to_str().unwrap()).unwrap();
+
+        let metadata = &output.file.metadata;
+        assert_eq!(metadata.name, "split");
+        assert_eq!(metadata.reference_file_name, "recording");
+        assert_eq!(metadata.reference_split_start_time, 1_000);
+        assert_eq!(metadata.reference_split_stop_time, 100_000);
+        assert_eq!(output.file.stream.metadata.sample_type, "Image");
+
+        let data = &output.file.stream.data;
+        let reference = &expected.file.stream.data;
+        assert_eq!(data.frame_size, common::frame_sizes(5));
+        assert_eq!(data.byte_offset, reference.byte_offset);
+        assert_eq!(data.timestamp, reference.timestamp);
+        assert_eq!(data.timestamp, vec![1000, 1100, 1200, 1300, 1400]);
+        assert_eq!(data.frame_number, vec![1, 2, 3, 4, 5]);
+
+        fs::remove_file(little).unwrap();
+        fs::remove_file(big).unwrap();
+    }
+}
+
+fn all_types() -> Vec<Object> {
+    vec![
+        Object::new("/'group'"),
+        Object::channel("/'group'/'i8'", Values::I8(vec![-128, 0, 127])),
+        Object::channel("/'group'/'i16'", Values::I16(vec![-300, 1, 300])),
+        Object::channel("/'group'/'i32'", Values::I32(vec![i32::MIN, -1, i32::MAX])),
+        Object::channel("/'group'/'i64'", Values::I64(vec![i64::MIN, -2, i64::MAX])),
+        Object::channel("/'group'/'u8'", Values::U8(vec![0, 1, 255])),
+        Object::channel("/'group'/'u16'", Values::U16(vec![0, 258, u16::MAX])),
+        Object::channel(
+            "/'group'/'u32'",
+            Values::U32(vec![0, 0x0102_0304, u32::MAX]),
+        ),
+        Object::channel(
+            "/'group'/'u64'",
+            Values::U64(vec![0, 0x0102_0304_0506_0708, u64::MAX]),
+        ),
+        Object::channel("/'group'/'f32'", Values::F32(vec![-1.5, 0.0, 3.25])),
+        Object::channel("/'group'/'f64'", Values::F64(vec![-1e300, 0.1, 2.5])),
+        Object::channel("/'group'/'bool'", Values::Boolean(vec![true, false, true])),
+        Object::channel(
+            "/'group'/'time'",
+            Values::TimeStamp(vec![(3_800_000_000, 1 << 63), (0, 1), (-1, u64::MAX)]),
+        ),
+        Object::channel(
+            "/'group'/'string'",
+            Values::String(vec!["a".to_string(), String::new(), "xyz".to_string()]),
+        ),
+    ]
+}
+
+#[test]
+fn decodes_every_raw_data_type_in_both_byte_orders() {
+    for big_endian in [false, true] {
+        let mut writer = TdmsWriter::new(big_endian);
+        writer.segment(&all_types(), false);
+        let path = writer.write(&format!("types_{}.tdms", big_endian));
+        let channels = read_channels(&path).unwrap();
+        let data: Vec<&ChannelData> = channels.iter().map(|c| &c.data).collect();
+
+        assert_eq!(data[0], &ChannelData::I8(vec![-128, 0, 127]));
+        assert_eq!(data[1], &ChannelData::I16(vec![-300, 1, 300]));
+        assert_eq!(data[2], &ChannelData::I32(vec![i32::MIN, -1, i32::MAX]));
+        assert_eq!(data[3], &ChannelData::I64(vec![i64::MIN, -2, i64::MAX]));
+        assert_eq!(data[4], &ChannelData::U8(vec![0, 1, 255]));
+        assert_eq!(data[5], &ChannelData::U16(vec![0, 258, u16::MAX]));
+        assert_eq!(data[6], &ChannelData::U32(vec![0, 0x0102_0304, u32::MAX]));
+        assert_eq!(
+            data[7],
+            &ChannelData::U64(vec![0, 0x0102_0304_0506_0708, u64::MAX])
+        );
+        assert_eq!(data[8], &ChannelData::F32(vec![-1.5, 0.0, 3.25]));
+        assert_eq!(data[9], &ChannelData::F64(vec![-1e300, 0.1, 2.5]));
+        assert_eq!(data[10], &ChannelData::Boolean(vec![true, false, true]));
+        assert_eq!(
+            data[11],
+            &ChannelData::TimeStamp(vec![
+                TdmsTimestamp {
+                    seconds: 3_800_000_000,
+                    fractions: 1 << 63
+                },
+                TdmsTimestamp {
+                    seconds: 0,
+                    fractions: 1
+                },
+                TdmsTimestamp {
+                    seconds: -1,
+                    fractions: u64::MAX
+                },
+            ])
+        );
+        assert_eq!(
+            data[12],
+            &ChannelData::String(vec!["a".to_string(), String::new(), "xyz".to_string()])
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn reads_properties_of_every_type_in_both_byte_orders() {
+    for big_endian in [false, true] {
+        let root = Object::new("/")
+            .property("i8", Property::Value(Values::I8(vec![-5])))
+            .property("i16", Property::Value(Values::I16(vec![-300])))
+            .property("i32", Property::Value(Values::I32(vec![-70_000])))
+            .property("u16", Property::Value(Values::U16(vec![513])))
+            .property("f64", Property::Value(Values::F64(vec![2.5])))
+            .property("bool", Property::Value(Values::Boolean(vec![true])))
+            .property(
+                "time",
+                Property::Value(Values::TimeStamp(vec![(3_800_000_000, 1 << 63)])),
+            )
+            .property("u64", Property::U64(0x0102_0304_0506_0708))
+            .string("text", "after the numbers");
+        let mut writer = TdmsWriter::new(big_endian);
+        writer.segment(&[root, Object::new("/'group'")], false);
+        let path = writer.write(&format!("properties_{}.tdms", big_endian));
+
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let segments = read_segments(&mut reader).unwrap();
+        assert_eq!(segments[0].lead_in.is_toc_big_endian, big_endian);
+        let metadata = segments[0].metadata.as_ref().unwrap();
+        let values: Vec<(&str, &str)> = metadata.objects[0]
+            .properties
+            .iter()
+            .map(|p| (p.name.as_str(), p.string_value.as_str()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("i8", "-5"),
+                ("i16", "-300"),
+                ("i32", "-70000"),
+                ("u16", "513"),
+                ("f64", "2.5"),
+                ("bool", "true"),
+                ("time", "2024-05-31T11:33:20.500000000Z"),
+                ("u64", "72623859790382856"),
+                ("text", "after the numbers"),
+            ]
+        );
+        assert_eq!(metadata.objects[1].path, "/'group'");
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn checks_the_tag_independent_of_the_byte_order() {
+    let mut writer = TdmsWriter::new(true);
+    writer.segment(&[Object::new("/")], false);
+    let lead_in = LeadIn::read(&mut &writer.bytes[..]).unwrap();
+    assert!(lead_in.is_toc_big_endian);
+    assert_eq!(lead_in.version, 4713);
+    assert_eq!(lead_in.raw_data_offset as usize, writer.bytes.len() - 28);
+
+    let mut bytes = writer.bytes.clone();
+    bytes[..4].copy_from_slice(b"mSDT");
+    assert!(matches!(
+        LeadIn::read(&mut &bytes[..]),
+        Err(TdmsParseError::IncorrectTdmsTag)
+    ));
+}
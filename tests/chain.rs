@@ -0,0 +1,240 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use adas_hvds::chain::{follow_chain, recording, recording_index_path, ChainIssueKind};
+
+use common::{hvds_segments_numbered, temp_path, Object, Property, TdmsWriter};
+
+/// The links and metadata of a split written by [`write_split`].
+struct SplitLinks<'a> {
+    before: &'a str,
+    after: &'a str,
+    reference: &'a str,
+    start: u64,
+    stop: u64,
+    first_frame_number: u32,
+}
+
+impl Default for SplitLinks<'_> {
+    fn default() -> Self {
+        SplitLinks {
+            before: "",
+            after: "",
+            reference: "recording",
+            start: 1_000,
+            stop: 2_000,
+            first_frame_number: 1,
+        }
+    }
+}
+
+/// A fresh directory for the splits of one test.
+fn chain_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a split with 3 frames to `<dir>/<name>.tdms`.
+fn write_split(dir: &Path, name: &str, links: SplitLinks) -> PathBuf {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments_numbered(&mut writer, 3, links.first_frame_number, false);
+    let root = Object::new("/")
+        .string("ReferenceFileName", links.reference)
+        .string("SplitBefore", links.before)
+        .string("SplitAfter", links.after)
+        .property("SplitStartTime", Property::U64(links.start))
+        .property("SplitStopTime", Property::U64(links.stop));
+    writer.segment(&[root], false);
+
+    let path = dir.join(format!("{}.tdms", name));
+    fs::write(&path, &writer.bytes).unwrap();
+    path
+}
+
+fn file_names(paths: impl Iterator<Item = PathBuf>) -> Vec<String> {
+    paths
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+#[test]
+fn follows_the_links_in_both_directions() {
+    let dir = chain_dir("chain_links");
+    write_split(
+        &dir,
+        "a",
+        SplitLinks {
+            after: "b",
+            ..SplitLinks::default()
+        },
+    );
+    let middle = write_split(
+        &dir,
+        "b",
+        SplitLinks {
+            before: "a",
+            after: "c.tdms",
+            start: 2_000,
+            stop: 3_000,
+            first_frame_number: 4,
+            ..SplitLinks::default()
+        },
+    );
+    write_split(
+        &dir,
+        "c",
+        SplitLinks {
+            before: "b",
+            start: 3_000,
+            stop: 4_000,
+            first_frame_number: 7,
+            ..SplitLinks::default()
+        },
+    );
+
+    let (splits, issues) = follow_chain(&middle).unwrap();
+    assert!(issues.is_empty(), "{:?}", issues);
+    let paths = splits.iter().map(|split| split.path.clone());
+    assert_eq!(file_names(paths), vec!["a.tdms", "b.tdms", "c.tdms"]);
+    assert_eq!(
+        recording_index_path(&splits),
+        Some(dir.join("a.recording.json"))
+    );
+
+    let recording = recording(&splits, issues).unwrap();
+    assert_eq!(recording.reference_file_name, "recording");
+    assert_eq!((recording.start_time, recording.stop_time), (1_000, 4_000));
+    assert_eq!(recording.frame_count, 9);
+    assert_eq!(recording.splits[2].first_frame_number, Some(7));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reports_a_link_that_is_not_returned() {
+    let dir = chain_dir("chain_broken_link");
+    let first = write_split(
+        &dir,
+        "a",
+        SplitLinks {
+            after: "b",
+            ..SplitLinks::default()
+        },
+    );
+    write_split(
+        &dir,
+        "b",
+        SplitLinks {
+            start: 2_000,
+            stop: 3_000,
+            first_frame_number: 4,
+            ..SplitLinks::default()
+        },
+    );
+
+    let (splits, issues) = follow_chain(&first).unwrap();
+    assert_eq!(splits.len(), 2);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, ChainIssueKind::BrokenLink);
+    assert_eq!(issues[0].raw_file, "b.tdms");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reports_discontinuities_between_splits() {
+    let dir = chain_dir("chain_discontinuities");
+    let first = write_split(
+        &dir,
+        "a",
+        SplitLinks {
+            after: "b",
+            ..SplitLinks::default()
+        },
+    );
+    write_split(
+        &dir,
+        "b",
+        SplitLinks {
+            before: "a",
+            reference: "other",
+            start: 2_500,
+            stop: 3_000,
+            first_frame_number: 10,
+            ..SplitLinks::default()
+        },
+    );
+
+    let (_, issues) = follow_chain(&first).unwrap();
+    let kinds: Vec<ChainIssueKind> = issues.iter().map(|issue| issue.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ChainIssueKind::DifferentRecording,
+            ChainIssueKind::TimeGap,
+            ChainIssueKind::FrameNumberGap
+        ]
+    );
+    assert!(issues.iter().all(|issue| issue.raw_file == "b.tdms"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stops_at_a_link_cycle() {
+    let dir = chain_dir("chain_cycle");
+    let first = write_split(
+        &dir,
+        "a",
+        SplitLinks {
+            before: "b",
+            after: "b",
+            ..SplitLinks::default()
+        },
+    );
+    write_split(
+        &dir,
+        "b",
+        SplitLinks {
+            before: "a",
+            after: "a",
+            ..SplitLinks::default()
+        },
+    );
+
+    let (splits, issues) = follow_chain(&first).unwrap();
+    let paths = splits.iter().map(|split| split.path.clone());
+    assert_eq!(file_names(paths), vec!["b.tdms", "a.tdms"]);
+    let cycles = issues
+        .iter()
+        .filter(|issue| issue.kind == ChainIssueKind::Cycle)
+        .count();
+    assert_eq!(cycles, 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reports_a_missing_split() {
+    let dir = chain_dir("chain_missing");
+    let first = write_split(
+        &dir,
+        "a",
+        SplitLinks {
+            after: "gone",
+            ..SplitLinks::default()
+        },
+    );
+
+    let (splits, issues) = follow_chain(&first).unwrap();
+    assert_eq!(splits.len(), 1);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, ChainIssueKind::MissingSplit);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn no_splits_have_no_recording() {
+    assert!(recording(&[], vec![]).is_none());
+    assert!(recording_index_path(&[]).is_none());
+}
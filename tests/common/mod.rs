@@ -0,0 +1,440 @@
+//! Writes synthetic TDMS files for the integration tests.
+#![allow(dead_code)]
+
+use std::convert::TryInto;
+use std::path::PathBuf;
+
+const TOC_METADATA: u32 = 1 << 1;
+const TOC_NEW_OBJECT_LIST: u32 = 1 << 2;
+const TOC_RAW_DATA: u32 = 1 << 3;
+const TOC_INTERLEAVED: u32 = 1 << 5;
+const TOC_BIG_ENDIAN: u32 = 1 << 6;
+
+/// Raw data values of a channel.
+#[derive(Debug, Clone)]
+pub enum Values {
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    Boolean(Vec<bool>),
+    /// (seconds since 1904, fractions)
+    TimeStamp(Vec<(i64, u64)>),
+    String(Vec<String>),
+}
+
+impl Values {
+    fn datatype(&self) -> u32 {
+        match self {
+            Values::I8(_) => 1,
+            Values::I16(_) => 2,
+            Values::I32(_) => 3,
+            Values::I64(_) => 4,
+            Values::U8(_) => 5,
+            Values::U16(_) => 6,
+            Values::U32(_) => 7,
+            Values::U64(_) => 8,
+            Values::F32(_) => 9,
+            Values::F64(_) => 10,
+            Values::String(_) => 0x20,
+            Values::Boolean(_) => 0x21,
+            Values::TimeStamp(_) => 0x44,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Values::I8(v) => v.len(),
+            Values::I16(v) => v.len(),
+            Values::I32(v) => v.len(),
+            Values::I64(v) => v.len(),
+            Values::U8(v) => v.len(),
+            Values::U16(v) => v.len(),
+            Values::U32(v) => v.len(),
+            Values::U64(v) => v.len(),
+            Values::F32(v) => v.len(),
+            Values::F64(v) => v.len(),
+            Values::Boolean(v) => v.len(),
+            Values::TimeStamp(v) => v.len(),
+            Values::String(v) => v.len(),
+        }
+    }
+
+    /// The encoded value `i`; strings are only encoded by `encode_all`.
+    fn encode(&self, i: usize, be: bool) -> Vec<u8> {
+        macro_rules! bytes {
+            ($v:expr) => {
+                if be {
+                    $v.to_be_bytes().to_vec()
+                } else {
+                    $v.to_le_bytes().to_vec()
+                }
+            };
+        }
+        match self {
+            Values::I8(v) => bytes!(v[i]),
+            Values::I16(v) => bytes!(v[i]),
+            Values::I32(v) => bytes!(v[i]),
+            Values::I64(v) => bytes!(v[i]),
+            Values::U8(v) => bytes!(v[i]),
+            Values::U16(v) => bytes!(v[i]),
+            Values::U32(v) => bytes!(v[i]),
+            Values::U64(v) => bytes!(v[i]),
+            Values::F32(v) => bytes!(v[i]),
+            Values::F64(v) => bytes!(v[i]),
+            Values::Boolean(v) => vec![v[i] as u8],
+            Values::TimeStamp(v) => {
+                let (seconds, fractions) = v[i];
+                bytes!(((seconds as u128) << 64) | fractions as u128)
+            }
+            Values::String(_) => unreachable!(),
+        }
+    }
+
+    fn encode_all(&self, be: bool) -> Vec<u8> {
+        match self {
+            Values::String(strings) => {
+                let mut table = vec![];
+                let mut text = vec![];
+                for s in strings {
+                    text.extend_from_slice(s.as_bytes());
+                    table.extend(u32_bytes(text.len() as u32, be));
+                }
+                table.extend(text);
+                table
+            }
+            _ => (0..self.len()).flat_map(|i| self.encode(i, be)).collect(),
+        }
+    }
+}
+
+fn u32_bytes(value: u32, be: bool) -> Vec<u8> {
+    if be {
+        value.to_be_bytes().to_vec()
+    } else {
+        value.to_le_bytes().to_vec()
+    }
+}
+
+fn u64_bytes(value: u64, be: bool) -> Vec<u8> {
+    if be {
+        value.to_be_bytes().to_vec()
+    } else {
+        value.to_le_bytes().to_vec()
+    }
+}
+
+/// A property value.
+#[derive(Debug, Clone)]
+pub enum Property {
+    String(String),
+    U32(u32),
+    U64(u64),
+    Value(Values),
+}
+
+/// An object in the metadata of a segment.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub path: String,
+    pub values: Option<Values>,
+    /// Writes the "same as previous" raw data index instead of a new one.
+    pub same_as_previous: bool,
+    pub properties: Vec<(String, Property)>,
+}
+
+impl Object {
+    pub fn new(path: &str) -> Self {
+        Object {
+            path: path.to_string(),
+            values: None,
+            same_as_previous: false,
+            properties: vec![],
+        }
+    }
+
+    /// A channel whose raw data index is the one of its previous segment.
+    pub fn same_as_previous(path: &str, values: Values) -> Self {
+        Object {
+            same_as_previous: true,
+            ..Object::channel(path, values)
+        }
+    }
+
+    pub fn channel(path: &str, values: Values) -> Self {
+        Object {
+            values: Some(values),
+            ..Object::new(path)
+        }
+    }
+
+    pub fn property(mut self, name: &str, value: Property) -> Self {
+        self.properties.push((name.to_string(), value));
+        self
+    }
+
+    pub fn string(self, name: &str, value: &str) -> Self {
+        self.property(name, Property::String(value.to_string()))
+    }
+}
+
+/// Builds a TDMS file segment by segment.
+#[derive(Debug, Default)]
+pub struct TdmsWriter {
+    pub big_endian: bool,
+    pub version: u32,
+    /// Array dimension written for every channel, the values are split into
+    /// `len / array_dimension` arrays.
+    pub array_dimension: u32,
+    pub bytes: Vec<u8>,
+    /// Position of the lead-in of the last segment.
+    pub last_segment: usize,
+}
+
+impl TdmsWriter {
+    pub fn new(big_endian: bool) -> Self {
+        TdmsWriter {
+            big_endian,
+            version: 4713,
+            array_dimension: 1,
+            bytes: vec![],
+            last_segment: 0,
+        }
+    }
+
+    /// Appends a segment with a new object list holding `objects`, the raw
+    /// data of the channels is written contiguously or interleaved.
+    pub fn segment(&mut self, objects: &[Object], interleaved: bool) -> &mut Self {
+        self.metadata_segment(objects, interleaved, TOC_NEW_OBJECT_LIST)
+    }
+
+    /// Appends a segment adding `objects` to the previous object list. Only
+    /// the raw data of `objects` is written.
+    pub fn appended_segment(&mut self, objects: &[Object]) -> &mut Self {
+        self.metadata_segment(objects, false, 0)
+    }
+
+    fn metadata_segment(&mut self, objects: &[Object], interleaved: bool, toc: u32) -> &mut Self {
+        let be = self.big_endian;
+        let mut metadata = u32_bytes(objects.len() as u32, be);
+        for object in objects {
+            metadata.extend(u32_bytes(object.path.len() as u32, be));
+            metadata.extend_from_slice(object.path.as_bytes());
+            match &object.values {
+                None => metadata.extend_from_slice(&[0xff; 4]),
+                Some(_) if object.same_as_previous => metadata.extend_from_slice(&[0; 4]),
+                Some(values) => {
+                    let string_size = match values {
+                        Values::String(_) => Some(values.encode_all(be).len() as u64),
+                        _ => None,
+                    };
+                    metadata.extend(u32_bytes(if string_size.is_some() { 28 } else { 20 }, be));
+                    metadata.extend(u32_bytes(values.datatype(), be));
+                    let dimension = self.array_dimension;
+                    metadata.extend(u32_bytes(dimension, be));
+                    metadata.extend(u64_bytes(values.len() as u64 / dimension as u64, be));
+                    if let Some(size) = string_size {
+                        metadata.extend(u64_bytes(size, be));
+                    }
+                }
+            }
+            metadata.extend(u32_bytes(object.properties.len() as u32, be));
+            for (name, value) in object.properties.iter() {
+                metadata.extend(u32_bytes(name.len() as u32, be));
+                metadata.extend_from_slice(name.as_bytes());
+                match value {
+                    Property::String(s) => {
+                        metadata.extend(u32_bytes(0x20, be));
+                        metadata.extend(u32_bytes(s.len() as u32, be));
+                        metadata.extend_from_slice(s.as_bytes());
+                    }
+                    Property::U32(v) => {
+                        metadata.extend(u32_bytes(7, be));
+                        metadata.extend(u32_bytes(*v, be));
+                    }
+                    Property::U64(v) => {
+                        metadata.extend(u32_bytes(8, be));
+                        metadata.extend(u64_bytes(*v, be));
+                    }
+                    Property::Value(values) => {
+                        metadata.extend(u32_bytes(values.datatype(), be));
+                        metadata.extend(values.encode(0, be));
+                    }
+                }
+            }
+        }
+
+        let channels: Vec<&Values> = objects.iter().filter_map(|o| o.values.as_ref()).collect();
+        let raw: Vec<u8> = if interleaved {
+            let rows = channels.first().map_or(0, |values| values.len());
+            (0..rows)
+                .flat_map(|row| {
+                    channels
+                        .iter()
+                        .flat_map(move |values| values.encode(row, be))
+                })
+                .collect()
+        } else {
+            channels
+                .iter()
+                .flat_map(|values| values.encode_all(be))
+                .collect()
+        };
+
+        let mut toc = toc | TOC_METADATA;
+        if !raw.is_empty() {
+            toc |= TOC_RAW_DATA;
+        }
+        if interleaved {
+            toc |= TOC_INTERLEAVED;
+        }
+        self.lead_in(toc, metadata.len() as u64, raw.len() as u64);
+        self.bytes.extend(metadata);
+        self.bytes.extend(raw);
+        self
+    }
+
+    /// Appends a segment without metadata, repeating the previous object list.
+    pub fn raw_segment(&mut self, raw: &[u8]) -> &mut Self {
+        self.lead_in(TOC_RAW_DATA, 0, raw.len() as u64);
+        self.bytes.extend_from_slice(raw);
+        self
+    }
+
+    /// Appends `raw` to the raw data of the last segment, e.g. a repetition of
+    /// its chunk.
+    pub fn extend_raw_data(&mut self, raw: &[u8]) -> &mut Self {
+        let start = self.last_segment + 12;
+        let offset = &mut self.bytes[start..start + 8];
+        let next_segment_offset = if self.big_endian {
+            u64::from_be_bytes(offset.try_into().unwrap())
+        } else {
+            u64::from_le_bytes(offset.try_into().unwrap())
+        };
+        offset.copy_from_slice(&u64_bytes(
+            next_segment_offset + raw.len() as u64,
+            self.big_endian,
+        ));
+        self.bytes.extend_from_slice(raw);
+        self
+    }
+
+    fn lead_in(&mut self, mut toc: u32, metadata_size: u64, raw_size: u64) {
+        self.last_segment = self.bytes.len();
+        let be = self.big_endian;
+        if be {
+            toc |= TOC_BIG_ENDIAN;
+        }
+        self.bytes.extend_from_slice(b"TDSm");
+        self.bytes.extend(toc.to_le_bytes());
+        self.bytes.extend(u32_bytes(self.version, be));
+        self.bytes.extend(u64_bytes(metadata_size + raw_size, be));
+        self.bytes.extend(u64_bytes(metadata_size, be));
+    }
+
+    pub fn write(&self, name: &str) -> PathBuf {
+        let path = temp_path(name);
+        std::fs::write(&path, &self.bytes).unwrap();
+        path
+    }
+}
+
+/// A file in the temporary directory unique to the test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("adas_hvds_{}_{}", std::process::id(), name))
+}
+
+/// Frame payload sizes of the synthetic HVDS files.
+pub fn frame_sizes(frames: usize) -> Vec<u64> {
+    (0..frames).map(|i| 100 + 10 * i as u64).collect()
+}
+
+/// Writes an HVDS file with `frames` frames.
+///
+/// The Data channel is split over two segments. The Timestamp channel follows
+/// in its own segment, then the Index and Header channels share a segment,
+/// both written with `interleaved` raw data.
+pub fn hvds_file(name: &str, frames: usize, big_endian: bool, interleaved: bool) -> PathBuf {
+    let mut writer = TdmsWriter::new(big_endian);
+    hvds_segments(&mut writer, frames, interleaved);
+    writer.write(&format!("{}.tdms", name))
+}
+
+/// Appends the segments of an HVDS file with `frames` frames to `writer`.
+pub fn hvds_segments(writer: &mut TdmsWriter, frames: usize, interleaved: bool) {
+    hvds_segments_numbered(writer, frames, 1, interleaved);
+}
+
+/// Like [`hvds_segments`], numbering the frames from `first_frame_number`.
+pub fn hvds_segments_numbered(
+    writer: &mut TdmsWriter,
+    frames: usize,
+    first_frame_number: u32,
+    interleaved: bool,
+) {
+    let sizes = frame_sizes(frames);
+    let data: Vec<u8> = sizes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &size)| std::iter::repeat_n(i as u8, size as usize))
+        .collect();
+    let half = data.len() / 2;
+
+    let mut timestamps = vec![];
+    let mut indices = vec![];
+    let mut headers = vec![];
+    let mut offset = 0;
+    for (i, size) in sizes.iter().enumerate() {
+        let start = 1_000 + 100 * i as u64;
+        timestamps.extend([start, start + 50, start, start + 50]);
+        indices.extend([offset as i64, (offset + size) as i64]);
+        headers.extend([1, first_frame_number + i as u32]);
+        offset += size;
+    }
+
+    let root = Object::new("/")
+        .string("name", "split")
+        .string("ReferenceFileName", "recording")
+        .property("SplitStartTime", Property::U64(1_000))
+        .property("SplitStopTime", Property::U64(100_000));
+    let stream = Object::new("/'Stream'")
+        .string("name", "Stream")
+        .string("SampleType", "Image");
+
+    writer
+        .segment(
+            &[
+                root,
+                stream,
+                Object::channel("/'Stream'/'Data'", Values::U8(data[..half].to_vec())),
+            ],
+            false,
+        )
+        .segment(
+            &[Object::channel(
+                "/'Stream'/'Data'",
+                Values::U8(data[half..].to_vec()),
+            )],
+            false,
+        )
+        .segment(
+            &[Object::channel(
+                "/'Stream'/'Timestamp'",
+                Values::U64(timestamps),
+            )],
+            interleaved,
+        )
+        .segment(
+            &[
+                Object::channel("/'Stream'/'Index'", Values::I64(indices)),
+                Object::channel("/'Stream'/'Header'", Values::U32(headers)),
+            ],
+            interleaved,
+        );
+}
@@ -0,0 +1,235 @@
+use adas_hvds::tdms_datatype::{DaqmxDataType, TdmsDataType};
+use adas_hvds::tdms_lead_in::TDMS_VERSION_2_0;
+use adas_hvds::tdms_metadata::Metadata;
+use adas_hvds::tdms_object::{DaqmxDigitalLineScaler, RawDataIndex};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+/// Builds the metadata part of a segment.
+struct MetadataWriter {
+    big_endian: bool,
+    bytes: Vec<u8>,
+}
+
+impl MetadataWriter {
+    fn new(big_endian: bool, object_count: u32) -> Self {
+        let mut writer = MetadataWriter {
+            big_endian,
+            bytes: vec![],
+        };
+        writer.u32(object_count);
+        writer
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        if self.big_endian {
+            self.bytes.extend_from_slice(&value.to_be_bytes());
+        } else {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        if self.big_endian {
+            self.bytes.extend_from_slice(&value.to_be_bytes());
+        } else {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        self
+    }
+
+    fn path(&mut self, path: &str) -> &mut Self {
+        self.u32(path.len() as u32);
+        self.bytes.extend_from_slice(path.as_bytes());
+        self
+    }
+
+    /// (datatype, raw buffer index, byte offset within stride, sample format bitmap, scale id)
+    fn format_changing(
+        &mut self,
+        path: &str,
+        scalers: &[(u32, u32, u32, u8, u32)],
+        widths: &[u32],
+    ) {
+        self.path(path);
+        self.u32(0x0000_1269).u32(0xffff_ffff).u32(1).u64(100);
+        self.u32(scalers.len() as u32);
+        for &(datatype, buffer, offset, bitmap, scale_id) in scalers {
+            self.u32(datatype).u32(buffer).u32(offset);
+            self.bytes.push(bitmap);
+            self.u32(scale_id);
+        }
+        self.u32(widths.len() as u32);
+        for &width in widths {
+            self.u32(width);
+        }
+        self.u32(0);
+    }
+
+    /// (datatype, raw buffer index, bit offset, sample format bitmap, scale id)
+    fn digital_line(&mut self, path: &str, scalers: &[(u32, u32, u32, u8, u32)], widths: &[u32]) {
+        self.path(path);
+        self.u32(0x0000_1369).u32(0xffff_ffff).u32(1).u64(100);
+        self.u32(scalers.len() as u32);
+        for &(datatype, buffer, bit_offset, bitmap, scale_id) in scalers {
+            self.u32(datatype).u32(buffer).u32(bit_offset);
+            self.bytes.push(bitmap);
+            self.u32(scale_id);
+        }
+        self.u32(widths.len() as u32);
+        for &width in widths {
+            self.u32(width);
+        }
+        self.u32(0);
+    }
+
+    fn plain(&mut self, path: &str) {
+        self.path(path);
+        self.u32(20).u32(TdmsDataType::U64 as u32).u32(1).u64(7);
+        self.u32(0);
+    }
+
+    fn read(&self) -> Metadata {
+        Metadata::read(&mut &self.bytes[..], self.big_endian, TDMS_VERSION_2_0).unwrap()
+    }
+}
+
+fn daqmx(metadata: &Metadata, object: usize) -> &adas_hvds::tdms_object::DaqmxScaler {
+    match &metadata.objects[object].raw_data_index {
+        RawDataIndex::Daqmx(daqmx) => daqmx,
+        other => panic!("expected a DAQmx raw data index, got {:?}", other),
+    }
+}
+
+fn digital_line(metadata: &Metadata, object: usize) -> &DaqmxDigitalLineScaler {
+    match &metadata.objects[object].raw_data_index {
+        RawDataIndex::DaqmxDigitalLine(daqmx) => daqmx,
+        other => panic!(
+            "expected a DAQmx digital line raw data index, got {:?}",
+            other
+        ),
+    }
+}
+
+#[test]
+fn reads_every_format_changing_scaler() {
+    let mut writer = MetadataWriter::new(false, 1);
+    writer.format_changing(
+        "/'group'/'ai'",
+        &[(5, 0, 0, 1, 10), (3, 0, 2, 2, 11), (9, 1, 4, 3, 12)],
+        &[6, 8],
+    );
+    let metadata = writer.read();
+
+    let daqmx = daqmx(&metadata, 0);
+    assert_eq!(daqmx.chunk_size, 100);
+    assert_eq!(daqmx.scalers_size, 3);
+    assert_eq!(daqmx.scalers.len(), 3);
+    let fields: Vec<(DaqmxDataType, u32, u32, u8, u32)> = daqmx
+        .scalers
+        .iter()
+        .map(|s| {
+            (
+                s.datatype.clone(),
+                s.raw_buffer_index,
+                s.raw_byte_offset_within_stride,
+                s.sample_format_bitmap,
+                s.scale_id,
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            (DaqmxDataType::I32, 0, 0, 1, 10),
+            (DaqmxDataType::I16, 0, 2, 2, 11),
+            (DaqmxDataType::DoubleFloat, 1, 4, 3, 12),
+        ]
+    );
+    assert_eq!(daqmx.raw_data_vector, vec![6, 8]);
+}
+
+#[test]
+fn objects_after_multi_scaler_object_stay_in_sync() {
+    let mut writer = MetadataWriter::new(false, 3);
+    writer.format_changing("/'group'/'ai0'", &[(5, 0, 0, 0, 1), (5, 0, 1, 0, 2)], &[2]);
+    writer.format_changing("/'group'/'ai1'", &[(3, 0, 0, 0, 3), (3, 0, 2, 0, 4)], &[4]);
+    writer.plain("/'group'/'plain'");
+    let metadata = writer.read();
+
+    assert_eq!(metadata.objects.len(), 3);
+    assert_eq!(metadata.objects[1].path, "/'group'/'ai1'");
+    let scale_ids: Vec<u32> = daqmx(&metadata, 1)
+        .scalers
+        .iter()
+        .map(|s| s.scale_id)
+        .collect();
+    assert_eq!(scale_ids, vec![3, 4]);
+    assert_eq!(daqmx(&metadata, 1).raw_data_vector, vec![4]);
+
+    assert_eq!(metadata.objects[2].path, "/'group'/'plain'");
+    match &metadata.objects[2].raw_data_index {
+        RawDataIndex::NewDataIndex(index) => {
+            assert_eq!(index.datatype, TdmsDataType::U64);
+            assert_eq!(index.chunk_size, 7);
+        }
+        other => panic!("expected a new raw data index, got {:?}", other),
+    }
+}
+
+#[test]
+fn reads_multi_scaler_object_big_endian() {
+    let mut writer = MetadataWriter::new(true, 2);
+    writer.format_changing("/'group'/'ai'", &[(5, 0, 0, 0, 1), (7, 2, 8, 1, 2)], &[12]);
+    writer.plain("/'group'/'plain'");
+    let metadata = writer.read();
+
+    let daqmx = daqmx(&metadata, 0);
+    assert_eq!(daqmx.scalers.len(), 2);
+    assert_eq!(daqmx.scalers[1].datatype, DaqmxDataType::I64);
+    assert_eq!(daqmx.scalers[1].raw_buffer_index, 2);
+    assert_eq!(daqmx.scalers[1].raw_byte_offset_within_stride, 8);
+    assert_eq!(daqmx.raw_data_vector, vec![12]);
+    assert_eq!(metadata.objects[1].path, "/'group'/'plain'");
+}
+
+#[test]
+fn reads_daqmx_markers_big_endian() {
+    let mut writer = MetadataWriter::new(true, 3);
+    writer.format_changing("/'group'/'ai'", &[(5, 0, 0, 0, 1)], &[4]);
+    writer.digital_line("/'group'/'di'", &[(0, 1, 9, 0, 2), (0, 1, 10, 0, 3)], &[2]);
+    writer.plain("/'group'/'plain'");
+    let metadata = writer.read();
+
+    assert_eq!(daqmx(&metadata, 0).scalers[0].scale_id, 1);
+    let lines = digital_line(&metadata, 1);
+    assert_eq!(lines.chunk_size, 100);
+    let bit_offsets: Vec<u32> = lines.scalers.iter().map(|s| s.raw_bit_offset).collect();
+    assert_eq!(bit_offsets, vec![9, 10]);
+    assert_eq!(lines.raw_data_vector, vec![2]);
+    assert_eq!(metadata.objects[2].path, "/'group'/'plain'");
+}
+
+#[test]
+fn rejects_unknown_daqmx_data_type() {
+    let mut writer = MetadataWriter::new(false, 1);
+    writer.format_changing("/'group'/'ai'", &[(0xc, 0, 0, 0, 1)], &[4]);
+
+    match Metadata::read(&mut &writer.bytes[..], false, TDMS_VERSION_2_0) {
+        Err(TdmsParseError::UnknownDaqmxDataType { code }) => assert_eq!(code, 0xc),
+        other => panic!("expected an unknown DAQmx data type, got {:?}", other),
+    }
+}
+
+#[test]
+fn truncated_digital_line_scaler_is_an_error() {
+    let mut writer = MetadataWriter::new(false, 1);
+    writer.digital_line("/'group'/'di'", &[(0, 0, 3, 0, 1)], &[1]);
+    // cut the metadata right before the sample format bitmap
+    let cut = writer.bytes.len() - 4 - 4 - 4 - 4 - 1;
+
+    match Metadata::read(&mut &writer.bytes[..cut], false, TDMS_VERSION_2_0) {
+        Err(TdmsParseError::IoError(_)) => {}
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::data_channel::{DataChannelMap, DataSegment};
+use adas_hvds::hvds::index;
+use adas_hvds::output::{ByteRange, FrameIssue};
+
+use common::{Object, TdmsWriter, Values};
+
+/// Two segments of 100 channel bytes each, at file positions 1000 and 2000.
+fn two_segments() -> DataChannelMap {
+    let mut map = DataChannelMap::default();
+    map.push(DataSegment {
+        absolute_pos: 1_000,
+        size: 100,
+    });
+    map.push(DataSegment {
+        absolute_pos: 2_000,
+        size: 100,
+    });
+    map
+}
+
+fn range(offset: u64, size: u64) -> ByteRange {
+    ByteRange { offset, size }
+}
+
+#[test]
+fn maps_a_range_within_a_segment() {
+    let map = two_segments();
+    assert_eq!(map.map_range(10..30), vec![range(1_010, 20)]);
+    assert_eq!(map.map_range(100..200), vec![range(2_000, 100)]);
+}
+
+#[test]
+fn splits_a_range_straddling_segments() {
+    let map = two_segments();
+    assert_eq!(
+        map.map_range(90..110),
+        vec![range(1_090, 10), range(2_000, 10)]
+    );
+}
+
+#[test]
+fn cuts_a_range_at_the_end_of_the_last_segment() {
+    let map = two_segments();
+    assert_eq!(map.map_range(190..250), vec![range(2_090, 10)]);
+    assert_eq!(
+        map.map_range(90..250),
+        vec![range(1_090, 10), range(2_000, 100)]
+    );
+    // a range starting past the end maps to an empty range at the end
+    assert_eq!(map.map_range(300..350), vec![range(2_100, 0)]);
+}
+
+#[test]
+fn reports_frames_ending_past_the_data_channel() {
+    let root = Object::new("/").string("name", "split");
+    let stream = Object::new("/'Stream'").string("name", "Stream");
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(
+            &[
+                root,
+                stream,
+                Object::channel("/'Stream'/'Data'", Values::U8(vec![0; 100])),
+            ],
+            false,
+        )
+        .segment(
+            &[
+                Object::channel(
+                    "/'Stream'/'Timestamp'",
+                    Values::U64(vec![1_000, 1_050, 1_000, 1_050, 1_100, 1_150, 1_100, 1_150]),
+                ),
+                Object::channel("/'Stream'/'Index'", Values::I64(vec![0, 60, 60, 150])),
+                Object::channel("/'Stream'/'Header'", Values::U32(vec![1, 1, 1, 2])),
+            ],
+            false,
+        );
+    let path = writer.write("past_data_end.tdms");
+
+    let output = index(path.to_str().unwrap()).unwrap();
+    let data = &output.file.stream.data;
+    assert_eq!(data.frame_size, vec![60, 40]);
+    assert_eq!(data.frame_issue(0), None);
+    assert_eq!(data.frame_issue(1), Some(FrameIssue::PastDataEnd));
+    assert_eq!(data.invalid_frames[0].end_offset, 150);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,142 @@
+use adas_hvds::diagnostics::{FrameNumberReport, SplitWindow, TimestampReport};
+
+/// Reset of the relative timestamps, 2021-01-01T00:00:00Z.
+const FUTURE_TIME_EVENT: u64 = 1_609_459_200_000_000_000;
+
+fn rows<T>(findings: &[T], row: impl Fn(&T) -> usize) -> Vec<usize> {
+    findings.iter().map(row).collect()
+}
+
+#[test]
+fn regular_timestamps_are_consistent() {
+    let report = TimestampReport::analyze(&[100, 200, 300, 400], &[150, 250, 350, 450], None);
+    assert!(report.is_consistent());
+    assert_eq!(report.first_timestamp, Some(100));
+    assert_eq!(report.last_timestamp, Some(400));
+
+    let period = report.period.unwrap();
+    assert_eq!((period.min, period.max, period.median), (100, 100, 100));
+    assert_eq!(period.mean, 100.0);
+    assert_eq!(period.jitter, 0.0);
+}
+
+#[test]
+fn reports_non_monotonic_start_timestamps() {
+    let report = TimestampReport::analyze(&[100, 200, 200, 150, 300], &[], None);
+    assert_eq!(report.non_monotonic_count, 2);
+    assert_eq!(rows(&report.non_monotonic, |f| f.row), vec![2, 3]);
+    assert_eq!(report.non_monotonic[1].timestamp, 150);
+    assert_eq!(report.non_monotonic[1].reference, 200);
+    assert!(!report.is_consistent());
+}
+
+#[test]
+fn end_timestamps_belong_to_the_last_rows() {
+    // only the last two rows have end timestamps
+    let report = TimestampReport::analyze(&[100, 200, 300, 400], &[290, 450], None);
+    assert_eq!(report.end_before_start_count, 1);
+    assert_eq!(report.end_before_start[0].row, 2);
+    assert_eq!(report.end_before_start[0].timestamp, 290);
+    assert_eq!(report.end_before_start[0].reference, 300);
+}
+
+#[test]
+fn reports_unusual_intervals() {
+    let report = TimestampReport::analyze(&[0, 100, 200, 240, 340, 600], &[], None);
+    let period = report.period.unwrap();
+    assert_eq!((period.min, period.max, period.median), (40, 260, 100));
+    assert_eq!(report.unusual_interval_count, 2);
+    assert_eq!(rows(&report.unusual_intervals, |f| f.row), vec![3, 5]);
+}
+
+#[test]
+fn checks_the_split_window_in_absolute_time() {
+    let split = SplitWindow {
+        future_time_event: FUTURE_TIME_EVENT,
+        range: FUTURE_TIME_EVENT + 100..=FUTURE_TIME_EVENT + 300,
+    };
+    let report = TimestampReport::analyze(&[50, 100, 200, 300, 350], &[], Some(split));
+    assert_eq!(report.outside_split_count, 2);
+    assert_eq!(rows(&report.outside_split, |f| f.row), vec![0, 4]);
+    assert_eq!(report.outside_split[0].timestamp, FUTURE_TIME_EVENT + 50);
+    assert_eq!(report.outside_split[0].reference, FUTURE_TIME_EVENT + 100);
+    assert_eq!(report.outside_split[1].reference, FUTURE_TIME_EVENT + 300);
+}
+
+#[test]
+fn timestamps_beyond_the_supported_time_range_are_outside_the_split() {
+    let split = SplitWindow {
+        future_time_event: FUTURE_TIME_EVENT,
+        range: FUTURE_TIME_EVENT..=i64::MAX as u64,
+    };
+    let report = TimestampReport::analyze(&[0, u64::MAX / 2], &[], Some(split));
+    assert_eq!(report.outside_split_count, 1);
+    assert_eq!(report.outside_split[0].row, 1);
+}
+
+#[test]
+fn empty_stream_has_no_period() {
+    let report = TimestampReport::analyze(&[], &[], None);
+    assert!(report.is_consistent());
+    assert!(report.period.is_none());
+    assert_eq!(report.first_timestamp, None);
+}
+
+#[test]
+fn continuous_frame_numbers_are_consistent() {
+    let report = FrameNumberReport::analyze(&[7, 8, 9, 10]);
+    assert!(report.is_consistent());
+    assert_eq!(report.first_frame_number, Some(7));
+    assert_eq!(report.last_frame_number, Some(10));
+    assert!(report.wrap_arounds.is_empty());
+}
+
+#[test]
+fn reports_gaps_as_dropped_frames() {
+    let report = FrameNumberReport::analyze(&[1, 2, 5, 6, 8]);
+    assert_eq!(report.gap_count, 2);
+    assert_eq!(report.dropped_frames, 3);
+    let gap = &report.gaps[0];
+    assert_eq!((gap.row, gap.first, gap.last, gap.missing), (2, 3, 4, 2));
+    assert_eq!(report.gaps[1].row, 4);
+}
+
+#[test]
+fn reports_duplicates() {
+    let report = FrameNumberReport::analyze(&[1, 2, 2, 3]);
+    assert_eq!(report.duplicate_count, 1);
+    assert_eq!(report.duplicates[0].row, 2);
+    assert_eq!(report.duplicates[0].previous, 2);
+    assert_eq!(report.gap_count, 0);
+}
+
+#[test]
+fn follows_a_wrap_around_of_the_counter() {
+    let report = FrameNumberReport::analyze(&[u32::MAX - 1, u32::MAX, 0, 1]);
+    assert!(report.is_consistent());
+    assert_eq!(report.wrap_arounds, vec![2]);
+
+    // a gap over the wrap-around
+    let report = FrameNumberReport::analyze(&[u32::MAX - 1, 1]);
+    let gap = &report.gaps[0];
+    assert_eq!((gap.first, gap.last, gap.missing), (u32::MAX, 0, 2));
+}
+
+#[test]
+fn a_single_late_frame_does_not_move_the_sequence() {
+    let report = FrameNumberReport::analyze(&[10, 11, 12, 9, 13, 14]);
+    assert_eq!(report.out_of_order_count, 1);
+    assert_eq!(report.out_of_order[0].row, 3);
+    assert_eq!(report.out_of_order[0].previous, 12);
+    assert_eq!(report.gap_count, 0);
+    assert_eq!(report.duplicate_count, 0);
+}
+
+#[test]
+fn continues_the_sequence_after_a_counter_reset() {
+    let report = FrameNumberReport::analyze(&[100, 101, 5, 6, 7]);
+    assert_eq!(report.out_of_order_count, 1);
+    assert_eq!(report.out_of_order[0].row, 2);
+    assert_eq!(report.gap_count, 0);
+    assert_eq!(report.duplicate_count, 0);
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use adas_hvds::extract::{extract, ExtractTarget, FrameSelection, FRAME_HEADER_SIZE};
+use adas_hvds::hvds::{index, write_output};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+use common::{frame_sizes, hvds_file, temp_path};
+
+/// The frame numbers of the frames in a concatenated extraction file.
+fn concatenated_frame_numbers(bytes: &[u8]) -> Vec<u32> {
+    let mut frame_numbers = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        frame_numbers.push(u32::from_le_bytes(
+            bytes[pos + 4..pos + 8].try_into().unwrap(),
+        ));
+        let size = u64::from_le_bytes(bytes[pos + 20..pos + 28].try_into().unwrap());
+        pos += FRAME_HEADER_SIZE + size as usize;
+    }
+    frame_numbers
+}
+
+fn extract_concatenated(path: &Path, selection: &FrameSelection, name: &str) -> Vec<u8> {
+    let target_path = temp_path(name);
+    let target = ExtractTarget::Concatenated(target_path.clone());
+    extract(path, None, selection, &target).unwrap();
+    let bytes = fs::read(&target_path).unwrap();
+    fs::remove_file(target_path).unwrap();
+    bytes
+}
+
+#[test]
+fn writes_the_framing_header() {
+    let path = hvds_file("extract_header", 3, false, false);
+    let selection = FrameSelection {
+        frame_numbers: Some(2..=2),
+        ..FrameSelection::default()
+    };
+    let bytes = extract_concatenated(&path, &selection, "extract_header.bin");
+
+    let size = frame_sizes(3)[1];
+    let mut expected = b"HVDF".to_vec();
+    expected.extend_from_slice(&[2, 0, 0, 0]);
+    expected.extend_from_slice(&[1, 0, 0, 0]);
+    expected.extend_from_slice(&[0x4C, 0x04, 0, 0, 0, 0, 0, 0]);
+    expected.extend_from_slice(&[size as u8, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(expected.len(), FRAME_HEADER_SIZE);
+    expected.extend(vec![1; size as usize]);
+    assert_eq!(bytes, expected);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn selects_frames_by_frame_number() {
+    let path = hvds_file("extract_frame_numbers", 5, false, false);
+    let selection = FrameSelection {
+        frame_numbers: Some(2..=4),
+        ..FrameSelection::default()
+    };
+    let bytes = extract_concatenated(&path, &selection, "extract_frame_numbers.bin");
+    assert_eq!(concatenated_frame_numbers(&bytes), vec![2, 3, 4]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn selects_frames_by_time() {
+    // the frames start at 1000, 1100, ...
+    let path = hvds_file("extract_time", 5, false, false);
+    let selection = FrameSelection {
+        time_range: Some(1_050..=1_300),
+        ..FrameSelection::default()
+    };
+    let bytes = extract_concatenated(&path, &selection, "extract_time.bin");
+    assert_eq!(concatenated_frame_numbers(&bytes), vec![2, 3, 4]);
+
+    let selection = FrameSelection {
+        time_range: Some(1_050..=1_300),
+        frame_numbers: Some(3..=10),
+        state: Some(1),
+    };
+    let bytes = extract_concatenated(&path, &selection, "extract_time_and_number.bin");
+    assert_eq!(concatenated_frame_numbers(&bytes), vec![3, 4]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn writes_one_file_per_frame_to_a_directory() {
+    let path = hvds_file("extract_directory", 3, false, false);
+    let dir = temp_path("extract_directory");
+    let count = extract(
+        &path,
+        None,
+        &FrameSelection::default(),
+        &ExtractTarget::Directory(dir.clone()),
+    )
+    .unwrap();
+    assert_eq!(count, 3);
+    let payload = fs::read(dir.join("frame_00000002_3.bin")).unwrap();
+    assert_eq!(payload, vec![2; frame_sizes(3)[2] as usize]);
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_an_index_with_columns_of_different_lengths() {
+    let path = hvds_file("extract_short_index", 3, false, false);
+    let mut output = index(path.to_str().unwrap()).unwrap();
+    output.file.stream.data.state.pop();
+    let index_path = temp_path("extract_short_index.hvds.json");
+    write_output(&index_path, &output).unwrap();
+
+    let selection = FrameSelection {
+        state: Some(1),
+        ..FrameSelection::default()
+    };
+    let target = ExtractTarget::Concatenated(temp_path("extract_short_index.bin"));
+    let result = extract(&path, Some(&index_path), &selection, &target);
+    assert!(matches!(result, Err(TdmsParseError::GeneralError { .. })));
+    assert!(!selection.matches(&output.file.stream.data, 2));
+    fs::remove_file(index_path).unwrap();
+    fs::remove_file(path).unwrap();
+}
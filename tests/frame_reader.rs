@@ -0,0 +1,106 @@
+use std::io::Cursor;
+
+use adas_hvds::frame_reader::FrameReader;
+use adas_hvds::output::{ByteRange, Data, FragmentedFrame};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+/// Frames of 4 bytes each filled with their row, with the given timestamps.
+fn frames(timestamps: &[u64]) -> (Data, Vec<u8>) {
+    let mut data = Data::with_capacity(timestamps.len());
+    let mut bytes = vec![];
+    for (row, &timestamp) in timestamps.iter().enumerate() {
+        data.byte_offset.push(bytes.len() as u64);
+        data.frame_size.push(4);
+        data.timestamp.push(timestamp);
+        data.timestamp_quality.push(0);
+        data.state.push(0);
+        data.frame_number.push(row as u32 + 1);
+        bytes.extend([row as u8; 4]);
+    }
+    (data, bytes)
+}
+
+fn reader(timestamps: &[u64]) -> FrameReader<Cursor<Vec<u8>>> {
+    let (data, bytes) = frames(timestamps);
+    FrameReader::new(Cursor::new(bytes), data).unwrap()
+}
+
+#[test]
+fn looks_up_sorted_timestamps() {
+    let reader = reader(&[100, 200, 200, 300, 400]);
+    assert_eq!(reader.row_at_time(0), 0);
+    assert_eq!(reader.row_at_time(200), 1);
+    assert_eq!(reader.row_at_time(250), 3);
+    assert_eq!(reader.row_at_time(500), 5);
+    assert_eq!(reader.rows_between(150, 300), vec![1, 2, 3]);
+    assert!(reader.rows_between(300, 150).is_empty());
+    assert!(reader.rows_between(410, 500).is_empty());
+}
+
+#[test]
+fn scans_unsorted_timestamps() {
+    // a clock reset after the third frame
+    let reader = reader(&[300, 400, 500, 100, 200, 350]);
+    assert_eq!(reader.row_at_time(150), 0);
+    assert_eq!(reader.row_at_time(450), 2);
+    assert_eq!(reader.row_at_time(600), 6);
+    assert_eq!(reader.rows_between(150, 350), vec![0, 4, 5]);
+    assert!(reader.rows_between(600, 700).is_empty());
+}
+
+#[test]
+fn reads_the_frames_within_a_time_range() {
+    let mut reader = reader(&[300, 400, 100, 200]);
+    let mut frames = reader.frames_between(150, 300);
+    assert_eq!(frames.remaining(), 2);
+
+    let mut rows = vec![];
+    while let Some(frame) = frames.next_frame() {
+        let frame = frame.unwrap();
+        assert_eq!(frame.payload, &[frame.row as u8; 4]);
+        rows.push((frame.row, frame.frame_number, frame.timestamp));
+    }
+    assert_eq!(rows, vec![(0, 1, 300), (3, 4, 200)]);
+}
+
+#[test]
+fn reads_all_frames_in_index_order() {
+    let mut reader = reader(&[300, 100, 200]);
+    let mut frames = reader.frames();
+    let mut rows = vec![];
+    while let Some(frame) = frames.next_frame() {
+        rows.push(frame.unwrap().row);
+    }
+    assert_eq!(rows, vec![0, 1, 2]);
+    assert_eq!(reader.frame(2).unwrap().unwrap().timestamp, 100);
+}
+
+#[test]
+fn rejects_columns_of_different_lengths() {
+    let (mut data, bytes) = frames(&[100, 200, 300]);
+    data.state.pop();
+    match FrameReader::new(Cursor::new(bytes), data) {
+        Err(TdmsParseError::GeneralError { message }) => {
+            assert!(message.contains("different lengths"), "{}", message)
+        }
+        other => panic!("expected an error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn rejects_byte_ranges_not_matching_the_frame_size() {
+    let (mut data, bytes) = frames(&[100, 200]);
+    data.fragmented_frames.push(FragmentedFrame {
+        row: 1,
+        ranges: vec![
+            ByteRange { offset: 4, size: 2 },
+            ByteRange { offset: 6, size: 6 },
+        ],
+    });
+    let mut reader = FrameReader::new(Cursor::new(bytes), data).unwrap();
+    assert_eq!(reader.frame_at(0).unwrap().payload, &[0; 4]);
+    assert!(matches!(
+        reader.frame_at(1),
+        Err(TdmsParseError::GeneralError { .. })
+    ));
+}
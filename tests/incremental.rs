@@ -0,0 +1,150 @@
+mod common;
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use adas_hvds::hvds::{index_file, IndexOptions};
+use adas_hvds::incremental::index_incremental;
+use adas_hvds::output::Data;
+
+use common::{hvds_segments, temp_path, TdmsWriter};
+
+fn hvds_bytes(frames: usize) -> Vec<u8> {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, frames, false);
+    writer.bytes
+}
+
+/// The end positions of the segments in `bytes`.
+fn segment_ends(bytes: &[u8]) -> Vec<usize> {
+    let mut ends = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let next_segment_offset = u64::from_le_bytes(bytes[pos + 12..pos + 20].try_into().unwrap());
+        pos += 28 + next_segment_offset as usize;
+        ends.push(pos);
+    }
+    ends
+}
+
+fn remove_index(path: &Path) {
+    fs::remove_file(path.with_extension("hvds.json")).unwrap();
+    fs::remove_file(path.with_extension("hvds.state.json")).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+/// The frames of `bytes` indexed in one go.
+fn complete_index(name: &str, bytes: &[u8]) -> Data {
+    let path = temp_path(name);
+    fs::write(&path, bytes).unwrap();
+    let output = index_file(&path, &IndexOptions::default()).unwrap();
+    fs::remove_file(path.with_extension("hvds.json")).unwrap();
+    fs::remove_file(path).unwrap();
+    output.file.stream.data
+}
+
+fn assert_same_frames(data: &Data, expected: &Data) {
+    assert_eq!(data.frame_number, expected.frame_number);
+    assert_eq!(data.byte_offset, expected.byte_offset);
+    assert_eq!(data.frame_size, expected.frame_size);
+    assert_eq!(data.timestamp, expected.timestamp);
+    assert_eq!(data.state, expected.state);
+}
+
+fn incremental_path(name: &str) -> PathBuf {
+    let path = temp_path(name);
+    let _ = fs::remove_file(path.with_extension("hvds.state.json"));
+    path
+}
+
+#[test]
+fn resumes_after_the_indexed_segments() {
+    let bytes = hvds_bytes(3);
+    let ends = segment_ends(&bytes);
+    let path = incremental_path("incremental_resume.tdms");
+    let options = IndexOptions::default();
+
+    // the timestamps are kept until the index segment has been written
+    fs::write(&path, &bytes[..ends[2]]).unwrap();
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 0);
+    assert!(output.file.stream.data.is_empty());
+
+    fs::write(&path, &bytes).unwrap();
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 3);
+    let expected = complete_index("incremental_resume_complete.tdms", &bytes);
+    assert_same_frames(&output.file.stream.data, &expected);
+
+    // nothing new
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 0);
+    assert_same_frames(&output.file.stream.data, &expected);
+    remove_index(&path);
+}
+
+#[test]
+fn leaves_a_segment_still_being_written_for_the_next_run() {
+    let bytes = hvds_bytes(2);
+    let path = incremental_path("incremental_unfinished.tdms");
+    let options = IndexOptions::default();
+
+    fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+    let (_, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 0);
+
+    fs::write(&path, &bytes).unwrap();
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(output.file.stream.data.frame_number, vec![1, 2]);
+    remove_index(&path);
+}
+
+#[test]
+fn writes_the_state_through_a_temporary_file() {
+    let path = incremental_path("incremental_state.tdms");
+    fs::write(&path, hvds_bytes(2)).unwrap();
+    index_incremental(&path, &IndexOptions::default()).unwrap();
+
+    let state_path = path.with_extension("hvds.state.json");
+    let state: serde_json::Value = serde_json::from_slice(&fs::read(&state_path).unwrap()).unwrap();
+    assert_eq!(state["FrameCount"], 2);
+    let mut temp_path = state_path.into_os_string();
+    temp_path.push(".tmp");
+    assert!(!Path::new(&temp_path).exists());
+    remove_index(&path);
+}
+
+#[test]
+fn names_the_raw_file_after_the_output_path() {
+    let path = incremental_path("incremental_output_path.tdms");
+    fs::write(&path, hvds_bytes(2)).unwrap();
+    let options = IndexOptions {
+        output_path: "renamed/recording.tdms".to_string(),
+        ..IndexOptions::default()
+    };
+
+    let (output, _) = index_incremental(&path, &options).unwrap();
+    assert_eq!(output.file.raw_file, "recording.tdms");
+    remove_index(&path);
+}
+
+#[test]
+fn reindexes_after_a_truncated_index() {
+    let bytes = hvds_bytes(3);
+    let path = incremental_path("incremental_truncated_index.tdms");
+    let options = IndexOptions::default();
+    fs::write(&path, &bytes).unwrap();
+    index_incremental(&path, &options).unwrap();
+
+    // an index cut short by an interrupted write
+    let json_path = path.with_extension("hvds.json");
+    let json = fs::read(&json_path).unwrap();
+    fs::write(&json_path, &json[..json.len() / 2]).unwrap();
+
+    let (output, count) = index_incremental(&path, &options).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(output.file.stream.data.frame_number, vec![1, 2, 3]);
+    remove_index(&path);
+}
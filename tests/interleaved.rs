@@ -0,0 +1,98 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::channel_decoder::{read_channels, ChannelData};
+use adas_hvds::hvds::index;
+
+use common::{frame_sizes, hvds_file, Object, TdmsWriter, Values};
+
+#[test]
+fn indexes_interleaved_channels_like_contiguous_ones() {
+    let frames = 6;
+    let contiguous = hvds_file("contiguous", frames, false, false);
+    let interleaved = hvds_file("interleaved", frames, false, true);
+    let expected = index(contiguous.to_str().unwrap()).unwrap();
+    let output = index(interleaved.to_str().unwrap()).unwrap();
+
+    let data = &output.file.stream.data;
+    assert_eq!(data.frame_size, frame_sizes(frames));
+    assert_eq!(data.timestamp, vec![1000, 1100, 1200, 1300, 1400, 1500]);
+    assert_eq!(data.frame_number, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(data.state, vec![1; frames]);
+    assert!(data.invalid_frames.is_empty());
+
+    let reference = &expected.file.stream.data;
+    assert_eq!(data.byte_offset, reference.byte_offset);
+    assert_eq!(data.frame_size, reference.frame_size);
+    assert_eq!(data.timestamp, reference.timestamp);
+    assert_eq!(data.frame_number, reference.frame_number);
+
+    // every payload byte holds the frame's row
+    let bytes = fs::read(&interleaved).unwrap();
+    for row in 0..frames {
+        for range in data.frame_ranges(row) {
+            let payload = &bytes[range.offset as usize..(range.offset + range.size) as usize];
+            assert!(payload.iter().all(|&b| b == row as u8), "frame {}", row);
+        }
+    }
+
+    fs::remove_file(contiguous).unwrap();
+    fs::remove_file(interleaved).unwrap();
+}
+
+fn channels() -> Vec<Object> {
+    vec![
+        Object::new("/'group'"),
+        Object::channel("/'group'/'a'", Values::U8(vec![1, 2, 3])),
+        Object::channel("/'group'/'b'", Values::I32(vec![-1, 70_000, -70_000])),
+        Object::channel("/'group'/'c'", Values::F64(vec![0.5, 1.5, 2.5])),
+        Object::channel(
+            "/'group'/'d'",
+            Values::TimeStamp(vec![(3_800_000_000, 1 << 63), (0, 0), (1, 2)]),
+        ),
+    ]
+}
+
+#[test]
+fn decodes_interleaved_generic_channels() {
+    for big_endian in [false, true] {
+        let mut writer = TdmsWriter::new(big_endian);
+        writer
+            .segment(&channels(), false)
+            .segment(&channels(), true);
+        let path = writer.write(&format!("generic_{}.tdms", big_endian));
+
+        let channels = read_channels(&path).unwrap();
+        let paths: Vec<&str> = channels.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/'group'/'a'",
+                "/'group'/'b'",
+                "/'group'/'c'",
+                "/'group'/'d'"
+            ]
+        );
+        assert_eq!(channels[0].data, ChannelData::U8(vec![1, 2, 3, 1, 2, 3]));
+        assert_eq!(
+            channels[1].data,
+            ChannelData::I32(vec![-1, 70_000, -70_000, -1, 70_000, -70_000])
+        );
+        assert_eq!(
+            channels[2].data,
+            ChannelData::F64(vec![0.5, 1.5, 2.5, 0.5, 1.5, 2.5])
+        );
+        match &channels[3].data {
+            ChannelData::TimeStamp(timestamps) => {
+                assert_eq!(timestamps.len(), 6);
+                assert_eq!(timestamps[3].seconds, 3_800_000_000);
+                assert_eq!(timestamps[3].fractions, 1 << 63);
+                assert_eq!(timestamps[5].seconds, 1);
+                assert_eq!(timestamps[5].fractions, 2);
+            }
+            other => panic!("expected timestamps, got {:?}", other),
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
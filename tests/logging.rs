@@ -0,0 +1,45 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::logging::{log_config, LOG_APPENDER};
+use log::{debug, info, LevelFilter};
+
+use common::temp_path;
+
+#[test]
+fn logs_json_lines_to_a_file() {
+    let path = temp_path("log.json");
+    let _ = fs::remove_file(&path);
+    let config = log_config(LevelFilter::Info, path.to_str().unwrap(), true)
+        .unwrap()
+        .unwrap();
+    assert_eq!(config.root().level(), LevelFilter::Info);
+    assert_eq!(config.root().appenders(), [LOG_APPENDER]);
+    assert!(path.is_file());
+
+    log4rs::init_config(config).unwrap();
+    debug!("not logged");
+    info!("indexing recording.tdms");
+    log::logger().flush();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let lines: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["level"], "INFO");
+    assert_eq!(lines[0]["message"], "indexing recording.tdms");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn nothing_is_logged_when_off() {
+    assert!(log_config(LevelFilter::Off, "stderr", false)
+        .unwrap()
+        .is_none());
+    assert!(log_config(LevelFilter::Info, "none", false)
+        .unwrap()
+        .is_none());
+}
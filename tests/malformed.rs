@@ -0,0 +1,148 @@
+mod common;
+
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::BufReader;
+
+use adas_hvds::channel_decoder::read_channels;
+use adas_hvds::hvds::{index, IndexOptions};
+use adas_hvds::incremental::index_incremental;
+use adas_hvds::tdms_parse_error::{ErrorCategory, TdmsParseError};
+use adas_hvds::tdms_segment::{is_complete, read_segments};
+
+use common::{Object, TdmsWriter, Values};
+
+fn channel() -> Vec<Object> {
+    vec![
+        Object::new("/'group'"),
+        Object::channel("/'group'/'values'", Values::U32(vec![1, 2, 3])),
+    ]
+}
+
+#[test]
+fn reports_truncated_metadata_as_io_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&channel(), false);
+    // cut the file in the middle of the raw data index
+    writer
+        .bytes
+        .truncate(28 + 4 + 4 + "/'group'".len() + 4 + 4 + 18);
+    let path = writer.write("truncated.tdms");
+
+    let error = read_channels(&path).unwrap_err();
+    assert!(matches!(error, TdmsParseError::IoError(_)), "{:?}", error);
+    assert_eq!(error.category(), ErrorCategory::Io);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_unknown_data_types() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&channel(), false);
+    // the data type follows the raw data index length of the channel
+    let path_end = writer
+        .bytes
+        .windows(b"/'group'/'values'".len())
+        .position(|window| window == b"/'group'/'values'")
+        .unwrap()
+        + b"/'group'/'values'".len();
+    writer.bytes[path_end + 4..path_end + 8].copy_from_slice(&0x77u32.to_le_bytes());
+    let path = writer.write("unknown_type.tdms");
+
+    let error = read_channels(&path).unwrap_err();
+    assert!(
+        matches!(error, TdmsParseError::UnknownDataType { code: 0x77 }),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.category(), ErrorCategory::UnsupportedStructure);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_invalid_property_values() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&[Object::new("/").string("SplitStartTime", "soon")], false);
+    let path = writer.write("invalid_property.tdms");
+
+    match index(path.to_str().unwrap()) {
+        Err(error @ TdmsParseError::InvalidPropertyValue { .. }) => {
+            assert_eq!(error.category(), ErrorCategory::UnsupportedStructure);
+            assert_eq!(
+                error.to_string(),
+                "Property SplitStartTime has the invalid value soon"
+            );
+        }
+        other => panic!("expected an invalid property, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_segment_offsets_past_the_address_range() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&channel(), false);
+    writer.bytes[12..20].copy_from_slice(&(u64::MAX - 10).to_le_bytes());
+    let path = writer.write("offset_overflow.tdms");
+
+    let mut reader = BufReader::new(File::open(&path).unwrap());
+    let error = read_segments(&mut reader).unwrap_err();
+    assert!(
+        matches!(error, TdmsParseError::InvalidSegmentOffsets { .. }),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.category(), ErrorCategory::UnsupportedStructure);
+    assert!(matches!(
+        is_complete(&mut reader),
+        Err(TdmsParseError::InvalidSegmentOffsets { .. })
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_raw_data_offsets_beyond_the_segment() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&channel(), false);
+    let next_segment_offset = u64::from_le_bytes(writer.bytes[12..20].try_into().unwrap());
+    writer.bytes[20..28].copy_from_slice(&(next_segment_offset + 1).to_le_bytes());
+    let path = writer.write("raw_data_offset.tdms");
+
+    match read_channels(&path) {
+        Err(TdmsParseError::InvalidSegmentOffsets {
+            next_segment_offset: next,
+            raw_data_offset,
+        }) => {
+            assert_eq!(next, next_segment_offset);
+            assert_eq!(raw_data_offset, next_segment_offset + 1);
+        }
+        other => panic!("expected invalid segment offsets, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn segment_offset_past_the_end_of_the_file_does_not_overflow() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&[Object::new("/").string("name", "split")], false);
+    // a metadata-only segment pointing just short of the end of the address range
+    writer.bytes[12..20].copy_from_slice(&(u64::MAX - 40).to_le_bytes());
+    writer.bytes[20..28].copy_from_slice(&0u64.to_le_bytes());
+    let path = writer.write("offset_past_end.tdms");
+
+    let mut reader = BufReader::new(File::open(&path).unwrap());
+    assert_eq!(read_segments(&mut reader).unwrap().len(), 1);
+    assert!(!is_complete(&mut reader).unwrap());
+    assert!(read_channels(&path).unwrap().is_empty());
+    assert!(index(&path.to_string_lossy())
+        .unwrap()
+        .file
+        .stream
+        .data
+        .is_empty());
+    let (_, count) = index_incremental(&path, &IndexOptions::default()).unwrap();
+    assert_eq!(count, 0);
+    fs::remove_file(path.with_extension("hvds.json")).unwrap();
+    fs::remove_file(path.with_extension("hvds.state.json")).unwrap();
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::merged_index::{
+    read_binary, MergedData, MergedFormat, MergedFrame, MergedIndex, MERGED_BINARY_VERSION,
+    MERGED_MAGIC,
+};
+use adas_hvds::output::{
+    ByteRange, Data, FragmentedFrame, FrameIssue, InvalidFrame, StreamMetadata,
+};
+
+use common::temp_path;
+
+/// `count` frames of 100 bytes, numbered from `first_frame`.
+fn data(first_frame: u32, count: usize) -> Data {
+    let mut data = Data::with_capacity(count);
+    for i in 0..count {
+        data.byte_offset.push(i as u64 * 100);
+        data.frame_size.push(100);
+        data.timestamp.push(1000 + i as u64);
+        data.timestamp_quality.push((i % 4) as u8);
+        data.state.push(7);
+        data.frame_number.push(first_frame + i as u32);
+    }
+    data
+}
+
+fn invalid_frame(row: usize) -> InvalidFrame {
+    InvalidFrame {
+        row,
+        start_offset: -100,
+        end_offset: 0,
+        issue: FrameIssue::NegativeOffset,
+    }
+}
+
+fn fragmented_frame(row: usize) -> FragmentedFrame {
+    FragmentedFrame {
+        row,
+        ranges: vec![
+            ByteRange {
+                offset: 10,
+                size: 60,
+            },
+            ByteRange {
+                offset: 200,
+                size: 40,
+            },
+        ],
+    }
+}
+
+#[test]
+fn appending_renumbers_the_rows() {
+    let mut first = data(1, 2);
+    first.invalid_frames.push(invalid_frame(1));
+    let mut second = data(3, 3);
+    second.invalid_frames.push(invalid_frame(0));
+    second.fragmented_frames.push(fragmented_frame(2));
+    second
+        .annotations
+        .insert("Stream/Note".to_string(), vec!["a".to_string(); 3]);
+
+    first.append(second);
+    assert_eq!(first.len(), 5);
+    assert_eq!(first.frame_number, vec![1, 2, 3, 4, 5]);
+    let invalid_rows: Vec<usize> = first.invalid_frames.iter().map(|f| f.row).collect();
+    assert_eq!(invalid_rows, vec![1, 2]);
+    assert_eq!(first.fragmented_frames[0].row, 4);
+    // annotations missing in the first part are filled with empty strings
+    assert_eq!(
+        first.annotations["Stream/Note"],
+        vec!["", "", "a", "a", "a"]
+    );
+}
+
+#[test]
+fn binary_index_round_trips() {
+    let mut frames = data(1, 2);
+    frames.append(data(3, 2));
+    frames.invalid_frames.push(invalid_frame(1));
+    frames.fragmented_frames.push(fragmented_frame(2));
+    let index = MergedIndex {
+        schema: String::new(),
+        reference_file_name: String::new(),
+        splits: vec!["a.tdms".to_string(), "b.tdms".to_string()],
+        metadata: StreamMetadata::new(),
+        data: MergedData {
+            split: vec![0, 0, 1, 1],
+            frames,
+        },
+    };
+    let path = temp_path("merged_round_trip.bin");
+    index.write(&path, MergedFormat::Binary).unwrap();
+
+    let binary = read_binary(&path).unwrap();
+    assert_eq!(binary.splits, index.splits);
+    assert_eq!(binary.frames.len(), 4);
+    assert_eq!(
+        binary.frames[2],
+        MergedFrame {
+            split: 1,
+            frame_number: 3,
+            state: 7,
+            flags: 1 << 3,
+            byte_offset: 0,
+            frame_size: 100,
+            timestamp: 1000,
+        }
+    );
+    assert!(binary.frames[1].is_invalid());
+    assert!(!binary.frames[1].is_fragmented());
+    assert!(binary.frames[2].is_fragmented());
+    assert_eq!(binary.fragmented_frames.len(), 1);
+    assert_eq!(binary.fragmented_frames[0].row, 2);
+    assert_eq!(
+        binary.fragmented_frames[0].ranges,
+        fragmented_frame(2).ranges
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn counts_beyond_the_file_length_are_an_error() {
+    let mut bytes = MERGED_MAGIC.to_vec();
+    bytes.extend_from_slice(&MERGED_BINARY_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    let path = temp_path("merged_corrupted.bin");
+    fs::write(&path, bytes).unwrap();
+
+    assert!(read_binary(&path).is_err());
+    fs::remove_file(path).unwrap();
+}
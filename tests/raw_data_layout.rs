@@ -0,0 +1,137 @@
+mod common;
+
+use std::convert::TryInto;
+use std::fs;
+
+use adas_hvds::channel_decoder::{read_channels, ChannelData, TdmsTimestamp};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+
+use common::{Object, TdmsWriter, Values};
+
+fn strings(values: &[&str]) -> Values {
+    Values::String(values.iter().map(|value| value.to_string()).collect())
+}
+
+/// The raw data of a string chunk: the end offsets of the values, then the text.
+fn string_chunk(offsets: &[u32], text: &str) -> Vec<u8> {
+    let mut raw: Vec<u8> = offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+    raw.extend_from_slice(text.as_bytes());
+    raw
+}
+
+fn expect_unsupported_raw_data(writer: &TdmsWriter, name: &str, expected: &str) {
+    let path = writer.write(name);
+    match read_channels(&path) {
+        Err(TdmsParseError::UnsupportedRawData { path, message }) => {
+            assert_eq!(path, "/'g'/'a'");
+            assert!(message.contains(expected), "{}", message);
+        }
+        other => panic!("expected unsupported raw data, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reads_repeated_chunks() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(
+            &[
+                Object::channel("/'g'/'a'", Values::U32(vec![1, 2])),
+                Object::channel("/'g'/'b'", Values::U16(vec![10, 20])),
+            ],
+            false,
+        )
+        .extend_raw_data(&[3, 0, 0, 0, 4, 0, 0, 0, 30, 0, 40, 0]);
+    let path = writer.write("repeated_chunks.tdms");
+
+    let channels = read_channels(&path).unwrap();
+    match (&channels[0].data, &channels[1].data) {
+        (ChannelData::U32(a), ChannelData::U16(b)) => {
+            assert_eq!(a, &vec![1, 2, 3, 4]);
+            assert_eq!(b, &vec![10, 20, 30, 40]);
+        }
+        other => panic!("expected U32 and U16 values, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reads_the_offset_table_of_every_string_chunk() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(&[Object::channel("/'g'/'a'", strings(&["ab", "c"]))], false)
+        .extend_raw_data(&string_chunk(&[1, 3], "def"));
+    let path = writer.write("string_chunks.tdms");
+
+    match &read_channels(&path).unwrap()[0].data {
+        ChannelData::String(values) => assert_eq!(values, &vec!["ab", "c", "d", "ef"]),
+        other => panic!("expected strings, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn string_offset_beyond_the_text_is_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(&[Object::channel("/'g'/'a'", strings(&["ab", "c"]))], false)
+        .extend_raw_data(&string_chunk(&[1, 5], "def"));
+    expect_unsupported_raw_data(&writer, "string_offset.tdms", "out of range");
+}
+
+#[test]
+fn decreasing_string_offsets_are_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(&[Object::channel("/'g'/'a'", strings(&["ab", "c"]))], false)
+        .extend_raw_data(&string_chunk(&[2, 1], "def"));
+    expect_unsupported_raw_data(&writer, "decreasing_offsets.tdms", "out of range");
+}
+
+#[test]
+fn chunk_larger_than_the_raw_data_is_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(
+        &[Object::channel("/'g'/'a'", Values::U32(vec![1, 2, 3]))],
+        false,
+    );
+    // drop the last value, leaving 8 of the 12 bytes of the chunk
+    writer.bytes.truncate(writer.bytes.len() - 4);
+    let next_segment_offset = u64::from_le_bytes(writer.bytes[12..20].try_into().unwrap());
+    writer.bytes[12..20].copy_from_slice(&(next_segment_offset - 4).to_le_bytes());
+    expect_unsupported_raw_data(&writer, "short_chunk.tdms", "exceeds");
+}
+
+#[test]
+fn channel_size_overflow_is_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(
+        &[Object::channel("/'g'/'a'", Values::U32(vec![1, 2, 3]))],
+        false,
+    );
+    // lead-in, object count, path, raw data index length, data type and array dimension
+    let number_of_values = 28 + 4 + 4 + "/'g'/'a'".len() + 4 + 4 + 4;
+    writer.bytes[number_of_values..number_of_values + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    expect_unsupported_raw_data(&writer, "size_overflow.tdms", "out of range");
+}
+
+#[test]
+fn timestamps_at_the_limits_do_not_overflow() {
+    let earliest = TdmsTimestamp {
+        seconds: i64::MIN,
+        fractions: 0,
+    };
+    assert_eq!(
+        earliest.unix_nanos(),
+        (i64::MIN as i128 - 2_082_844_800) * 1_000_000_000
+    );
+    assert_eq!(earliest.to_iso8601(), format!("{}.{:020}", i64::MIN, 0));
+
+    let unix_epoch = TdmsTimestamp {
+        seconds: 2_082_844_800,
+        fractions: 1 << 63,
+    };
+    assert_eq!(unix_epoch.unix_nanos(), 500_000_000);
+    assert_eq!(unix_epoch.to_iso8601(), "1970-01-01T00:00:00.500000000Z");
+}
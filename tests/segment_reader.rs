@@ -0,0 +1,172 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use adas_hvds::channel_decoder::{read_channels, ChannelData};
+use adas_hvds::tdms_datatype::TdmsDataType;
+use adas_hvds::tdms_metadata::Metadata;
+use adas_hvds::tdms_object::RawDataIndex;
+use adas_hvds::tdms_parse_error::TdmsParseError;
+use adas_hvds::tdms_segment::SegmentReader;
+
+use common::{Object, TdmsWriter, Values};
+
+/// The resolved metadata of every segment of `path`.
+fn resolve_segments(path: &Path) -> Result<Vec<Metadata>, TdmsParseError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let file_length = reader.seek(SeekFrom::End(0))?;
+    let mut segment_reader = SegmentReader::new();
+    let mut segments = vec![];
+    let mut pos = 0;
+    while pos < file_length {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (lead_in, metadata) = segment_reader.read_header(&mut reader)?;
+        segments.push(metadata);
+        match lead_in.next_segment_pos(pos)? {
+            Some(next_pos) => pos = next_pos,
+            None => break,
+        }
+    }
+    Ok(segments)
+}
+
+fn paths(metadata: &Metadata) -> Vec<&str> {
+    metadata.objects.iter().map(|o| o.path.as_str()).collect()
+}
+
+fn u32_raw(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[test]
+fn resolves_same_as_previous_raw_data_index() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(
+            &[Object::channel("/'g'/'a'", Values::U32(vec![1, 2, 3]))],
+            false,
+        )
+        .segment(
+            &[Object::same_as_previous(
+                "/'g'/'a'",
+                Values::U32(vec![4, 5, 6]),
+            )],
+            false,
+        );
+    let path = writer.write("same_as_previous.tdms");
+
+    let segments = resolve_segments(&path).unwrap();
+    match &segments[1].objects[0].raw_data_index {
+        RawDataIndex::NewDataIndex(index) => {
+            assert_eq!(index.datatype, TdmsDataType::U32);
+            assert_eq!(index.chunk_size, 3);
+        }
+        other => panic!("expected the previous raw data index, got {:?}", other),
+    }
+    match &read_channels(&path).unwrap()[0].data {
+        ChannelData::U32(values) => assert_eq!(values, &vec![1, 2, 3, 4, 5, 6]),
+        other => panic!("expected U32 values, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn segment_without_metadata_repeats_the_object_list() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(
+            &[
+                Object::channel("/'g'/'a'", Values::U32(vec![1, 2])),
+                Object::channel("/'g'/'b'", Values::U32(vec![10, 20])),
+            ],
+            false,
+        )
+        .raw_segment(&u32_raw(&[3, 4, 30, 40]));
+    let path = writer.write("raw_segment.tdms");
+
+    let segments = resolve_segments(&path).unwrap();
+    assert_eq!(paths(&segments[1]), vec!["/'g'/'a'", "/'g'/'b'"]);
+    let channels = read_channels(&path).unwrap();
+    match (&channels[0].data, &channels[1].data) {
+        (ChannelData::U32(a), ChannelData::U32(b)) => {
+            assert_eq!(a, &vec![1, 2, 3, 4]);
+            assert_eq!(b, &vec![10, 20, 30, 40]);
+        }
+        other => panic!("expected U32 values, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn objects_are_appended_without_a_new_object_list() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(&[Object::channel("/'g'/'a'", Values::U32(vec![1]))], false)
+        .appended_segment(&[Object::channel("/'g'/'b'", Values::U32(vec![2]))])
+        .segment(&[Object::channel("/'g'/'c'", Values::U32(vec![3]))], false);
+    let path = writer.write("appended_objects.tdms");
+
+    let segments = resolve_segments(&path).unwrap();
+    assert_eq!(paths(&segments[0]), vec!["/'g'/'a'"]);
+    assert_eq!(paths(&segments[1]), vec!["/'g'/'a'", "/'g'/'b'"]);
+    // a new object list replaces the previous one
+    assert_eq!(paths(&segments[2]), vec!["/'g'/'c'"]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn properties_accumulate_over_segments() {
+    let mut writer = TdmsWriter::new(false);
+    writer
+        .segment(
+            &[Object::channel("/'g'/'a'", Values::U32(vec![1]))
+                .string("unit", "m")
+                .string("source", "lidar")],
+            false,
+        )
+        .appended_segment(&[Object::new("/'g'/'a'").string("unit", "mm")]);
+    let path = writer.write("accumulated_properties.tdms");
+
+    let segments = resolve_segments(&path).unwrap();
+    let properties: Vec<(&str, &str)> = segments[1].objects[0]
+        .properties
+        .iter()
+        .map(|p| (p.name.as_str(), p.string_value.as_str()))
+        .collect();
+    assert_eq!(properties, vec![("unit", "mm"), ("source", "lidar")]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn same_as_previous_without_previous_index_is_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(
+        &[Object::same_as_previous("/'g'/'a'", Values::U32(vec![1]))],
+        false,
+    );
+    let path = writer.write("no_previous_index.tdms");
+
+    match resolve_segments(&path) {
+        Err(TdmsParseError::NoPreviousRawDataIndex { path }) => assert_eq!(path, "/'g'/'a'"),
+        other => panic!(
+            "expected a missing previous raw data index, got {:?}",
+            other
+        ),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn raw_data_without_any_metadata_is_an_error() {
+    let mut writer = TdmsWriter::new(false);
+    writer.raw_segment(&u32_raw(&[1, 2]));
+    let path = writer.write("no_metadata.tdms");
+
+    match resolve_segments(&path) {
+        Err(TdmsParseError::NoMetadata) => {}
+        other => panic!("expected missing metadata, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use adas_hvds::output::StreamMetadata;
+use adas_hvds::state_decoder::{StateDecoder, StateDefinitions};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+use serde_json::json;
+
+fn example() -> StateDecoder {
+    StateDecoder::load(Path::new("schema/state_definitions.example.json")).unwrap()
+}
+
+fn stream(sample_type: &str, version: &str) -> StreamMetadata {
+    let mut metadata = StreamMetadata::new();
+    metadata.sample_type = sample_type.to_string();
+    metadata.sample_type_version = version.to_string();
+    metadata
+}
+
+fn decoder(definition: serde_json::Value) -> Result<StateDecoder, TdmsParseError> {
+    let definitions: StateDefinitions =
+        serde_json::from_value(json!({ "Definitions": [definition] })).unwrap();
+    StateDecoder::new(definitions)
+}
+
+#[test]
+fn prefers_the_definition_of_the_exact_version() {
+    let decoder = example();
+    let exact = decoder.definition("Image", "1").unwrap();
+    assert_eq!(exact.sample_type_version.as_deref(), Some("1"));
+    let any = decoder.definition("Image", "2").unwrap();
+    assert_eq!(any.sample_type_version, None);
+    assert!(decoder.definition("Radar", "1").is_none());
+}
+
+#[test]
+fn counts_flags_and_field_values() {
+    let states = [0x0001, 0x0003, 0x0201, 0x1005, 0x0000];
+    let summary = example().summarize(&stream("Image", "1"), &states).unwrap();
+
+    assert_eq!(summary.frame_count, 5);
+    let flags: Vec<(&str, usize)> = summary
+        .flags
+        .iter()
+        .map(|flag| (flag.name.as_str(), flag.count))
+        .collect();
+    assert_eq!(flags, vec![("Valid", 4), ("Overflow", 1), ("Truncated", 1)]);
+
+    let values: Vec<(u32, Option<&str>, usize)> = summary.fields[0]
+        .values
+        .iter()
+        .map(|value| (value.value, value.name.as_deref(), value.count))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (0, Some("None"), 3),
+            (2, Some("LinkDown"), 1),
+            (0x10, None, 1)
+        ]
+    );
+    assert_eq!(summary.undefined_bits_count, 0);
+}
+
+#[test]
+fn counts_frames_with_undefined_bits() {
+    let summary = example()
+        .summarize(&stream("Image", "2"), &[0x1, 0x2, 0x8000_0001])
+        .unwrap();
+    assert_eq!(summary.undefined_bits_count, 2);
+}
+
+#[test]
+fn streams_without_definition_have_no_summary() {
+    assert!(example().summarize(&stream("Radar", "1"), &[1]).is_none());
+}
+
+#[test]
+fn field_extends_to_the_last_bit() {
+    let decoder = decoder(json!({
+        "SampleType": "Image",
+        "Fields": [{ "Name": "Code", "Shift": 28, "Width": 4 }]
+    }))
+    .unwrap();
+    let summary = decoder
+        .summarize(&stream("Image", "1"), &[0xF000_0000])
+        .unwrap();
+    assert_eq!(summary.fields[0].values[0].value, 0xF);
+}
+
+#[test]
+fn rejects_fields_beyond_the_state_word() {
+    for (shift, width) in [(30, 4), (0, 0), (1, u32::MAX)] {
+        let result = decoder(json!({
+            "SampleType": "Image",
+            "Fields": [{ "Name": "Code", "Shift": shift, "Width": width }]
+        }));
+        assert!(
+            matches!(result, Err(TdmsParseError::GeneralError { .. })),
+            "shift {} width {}",
+            shift,
+            width
+        );
+    }
+}
+
+#[test]
+fn rejects_flags_beyond_the_state_word() {
+    let result = decoder(json!({
+        "SampleType": "Image",
+        "Flags": [{ "Name": "Valid", "Bit": 32 }]
+    }));
+    assert!(matches!(result, Err(TdmsParseError::GeneralError { .. })));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use std::fs;
+
+use adas_hvds::channel_decoder::{read_channels, ChannelData};
+use adas_hvds::hvds::index;
+use adas_hvds::inspect::inspect;
+use adas_hvds::tdms_lead_in::{TDMS_VERSION_1_0, TDMS_VERSION_2_0};
+use adas_hvds::tdms_parse_error::{ErrorCategory, TdmsParseError};
+
+use common::{hvds_segments, Object, TdmsWriter, Values};
+
+fn channel() -> Vec<Object> {
+    vec![
+        Object::new("/'group'"),
+        Object::channel("/'group'/'values'", Values::U16(vec![1, 2, 3, 4, 5, 6])),
+    ]
+}
+
+#[test]
+fn reports_the_format_version_of_every_segment() {
+    let mut writer = TdmsWriter::new(false);
+    writer.version = TDMS_VERSION_1_0;
+    writer.segment(&channel(), false);
+    writer.version = TDMS_VERSION_2_0;
+    writer.segment(&channel(), false);
+    let path = writer.write("versions.tdms");
+
+    let reports = inspect(path.to_str().unwrap()).unwrap();
+    let versions: Vec<(u32, &str)> = reports
+        .iter()
+        .map(|r| (r.version, r.format_version))
+        .collect();
+    assert_eq!(versions, vec![(4712, "1.0"), (4713, "2.0")]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let mut writer = TdmsWriter::new(false);
+    writer.version = 4714;
+    writer.segment(&channel(), false);
+    let path = writer.write("version_4714.tdms");
+
+    match read_channels(&path) {
+        Err(error @ TdmsParseError::UnsupportedTdmsVersion { version: 4714 }) => {
+            assert_eq!(error.category(), ErrorCategory::UnsupportedStructure)
+        }
+        other => panic!("expected an unsupported version, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reads_arrays_of_values_in_tdms_1_0() {
+    let mut writer = TdmsWriter::new(false);
+    writer.version = TDMS_VERSION_1_0;
+    writer.array_dimension = 2;
+    writer.segment(&channel(), false);
+    let path = writer.write("array_1_0.tdms");
+
+    let channels = read_channels(&path).unwrap();
+    assert_eq!(channels[0].data, ChannelData::U16(vec![1, 2, 3, 4, 5, 6]));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_arrays_of_values_in_tdms_2_0() {
+    let mut writer = TdmsWriter::new(false);
+    writer.array_dimension = 2;
+    writer.segment(&channel(), false);
+    let path = writer.write("array_2_0.tdms");
+
+    assert!(matches!(
+        read_channels(&path),
+        Err(TdmsParseError::IncorrectArrayDimensionInTdmsObject)
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn writes_the_file_version_to_the_index() {
+    for version in [TDMS_VERSION_1_0, TDMS_VERSION_2_0] {
+        let mut writer = TdmsWriter::new(false);
+        writer.version = version;
+        hvds_segments(&mut writer, 3, false);
+        let path = writer.write(&format!("hvds_{}.tdms", version));
+
+        let output = index(path.to_str().unwrap()).unwrap();
+        assert_eq!(output.file.metadata.tdms_file_version, version);
+        assert_eq!(output.file.stream.data.frame_number, vec![1, 2, 3]);
+        fs::remove_file(path).unwrap();
+    }
+}
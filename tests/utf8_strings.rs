@@ -0,0 +1,109 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use adas_hvds::channel_decoder::{read_channels, read_channels_with, ChannelData};
+use adas_hvds::tdms_parse_error::TdmsParseError;
+use adas_hvds::tdms_segment::{read_segments, read_segments_with};
+use adas_hvds::util::StringDecoding;
+
+use common::{Object, TdmsWriter, Values};
+
+fn objects() -> Vec<Object> {
+    vec![
+        Object::new("/").string("CarModel", "Größe Ü"),
+        Object::new("/'Grüße'"),
+        Object::channel(
+            "/'Grüße'/'notes'",
+            Values::String(vec!["ok".to_string(), "€uro".to_string()]),
+        ),
+    ]
+}
+
+/// Writes the objects and replaces the first byte of `marker` with 0xff.
+fn write_broken(name: &str, marker: &str) -> (PathBuf, u64) {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&objects(), false);
+    let offset = writer
+        .bytes
+        .windows(marker.len())
+        .position(|window| window == marker.as_bytes())
+        .unwrap();
+    writer.bytes[offset] = 0xff;
+    (writer.write(name), offset as u64)
+}
+
+#[test]
+fn decodes_multibyte_utf8() {
+    let mut writer = TdmsWriter::new(false);
+    writer.segment(&objects(), false);
+    let path = writer.write("utf8.tdms");
+
+    let mut reader = BufReader::new(File::open(&path).unwrap());
+    let segments = read_segments(&mut reader).unwrap();
+    let metadata = segments[0].metadata.as_ref().unwrap();
+    assert_eq!(metadata.objects[0].properties[0].string_value, "Größe Ü");
+    assert_eq!(metadata.objects[1].path, "/'Grüße'");
+
+    let channels = read_channels(&path).unwrap();
+    assert_eq!(channels[0].path, "/'Grüße'/'notes'");
+    assert_eq!(
+        channels[0].data,
+        ChannelData::String(vec!["ok".to_string(), "€uro".to_string()])
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_invalid_properties_with_object_path_and_offset() {
+    let (path, offset) = write_broken("broken_property.tdms", "Ü");
+
+    match read_channels(&path) {
+        Err(TdmsParseError::InvalidUtf8 {
+            path: object,
+            offset: error_offset,
+        }) => {
+            assert_eq!(object, "/");
+            assert_eq!(error_offset, offset);
+        }
+        other => panic!("expected invalid UTF-8, got {:?}", other),
+    }
+
+    let mut reader = BufReader::new(File::open(&path).unwrap());
+    let segments = read_segments_with(&mut reader, StringDecoding::Lossy).unwrap();
+    let metadata = segments[0].metadata.as_ref().unwrap();
+    assert_eq!(
+        metadata.objects[0].properties[0].string_value,
+        "Größe \u{fffd}\u{fffd}"
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reports_invalid_string_values_with_channel_path_and_offset() {
+    let (path, offset) = write_broken("broken_channel.tdms", "€");
+
+    match read_channels(&path) {
+        Err(TdmsParseError::InvalidUtf8 {
+            path: channel,
+            offset: error_offset,
+        }) => {
+            assert_eq!(channel, "/'Grüße'/'notes'");
+            assert_eq!(error_offset, offset);
+        }
+        other => panic!("expected invalid UTF-8, got {:?}", other),
+    }
+
+    let channels = read_channels_with(&path, StringDecoding::Lossy).unwrap();
+    match &channels[0].data {
+        ChannelData::String(values) => {
+            assert_eq!(values[0], "ok");
+            assert!(values[1].starts_with('\u{fffd}'));
+            assert!(values[1].ends_with("uro"));
+        }
+        other => panic!("expected strings, got {:?}", other),
+    }
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use adas_hvds::tdms_parse_error::TdmsParseError;
+use adas_hvds::watch::{WatchOptions, WatchState, Watcher};
+
+use common::{hvds_segments, temp_path, TdmsWriter};
+
+/// An empty directory holding the watched files and the state file.
+fn watch_dir(name: &str) -> PathBuf {
+    let dir = temp_path(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn hvds_bytes(frames: usize) -> Vec<u8> {
+    let mut writer = TdmsWriter::new(false);
+    hvds_segments(&mut writer, frames, false);
+    writer.bytes
+}
+
+fn watcher(dir: &Path, state_file: PathBuf) -> Watcher {
+    Watcher::new(WatchOptions {
+        dirs: vec![dir.to_path_buf()],
+        state_file,
+        settle_time: Duration::ZERO,
+        poll_interval: Duration::ZERO,
+    })
+    .unwrap()
+}
+
+/// Polls once with an index function counting its calls.
+fn poll(watcher: &mut Watcher, calls: &Cell<usize>) -> usize {
+    watcher.poll(|_| {
+        calls.set(calls.get() + 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn indexes_a_file_once_it_has_settled() {
+    let dir = watch_dir("watch_settled");
+    let state_file = dir.join("state.json");
+    fs::write(dir.join("a.tdms"), hvds_bytes(2)).unwrap();
+    let mut watcher = watcher(&dir, state_file.clone());
+    let calls = Cell::new(0);
+
+    // the first scan only records the size
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(poll(&mut watcher, &calls), 1);
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(calls.get(), 1);
+
+    let state = WatchState::load(&state_file).unwrap();
+    assert_eq!(state.indexed.len(), 1);
+    // a restarted watcher does not index the file again
+    let mut restarted = self::watcher(&dir, state_file);
+    assert_eq!(poll(&mut restarted, &calls), 0);
+    assert_eq!(poll(&mut restarted, &calls), 0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn waits_while_a_file_grows() {
+    let dir = watch_dir("watch_growing");
+    let path = dir.join("a.tdms");
+    let bytes = hvds_bytes(2);
+    fs::write(&path, &bytes[..100]).unwrap();
+    let mut watcher = watcher(&dir, dir.join("state.json"));
+    let calls = Cell::new(0);
+
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    fs::write(&path, &bytes[..200]).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    // settled, but the last segment is incomplete
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    fs::write(&path, &bytes).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(poll(&mut watcher, &calls), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn retries_a_failed_file_once_it_changes() {
+    let dir = watch_dir("watch_failed");
+    let path = dir.join("a.tdms");
+    fs::write(&path, hvds_bytes(2)).unwrap();
+    let mut watcher = watcher(&dir, dir.join("state.json"));
+    let calls = Cell::new(0);
+    let failing = |_: &Path| {
+        calls.set(calls.get() + 1);
+        Err(TdmsParseError::UnexpectedChannel)
+    };
+
+    assert_eq!(watcher.poll(failing), 0);
+    assert_eq!(watcher.poll(failing), 0);
+    assert_eq!(watcher.poll(failing), 0);
+    assert_eq!(calls.get(), 1);
+
+    fs::write(&path, hvds_bytes(3)).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(poll(&mut watcher, &calls), 1);
+    assert_eq!(calls.get(), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keeps_running_when_the_state_file_cannot_be_written() {
+    let dir = watch_dir("watch_state_error");
+    let state_dir = dir.join("state");
+    fs::write(dir.join("a.tdms"), hvds_bytes(2)).unwrap();
+    let mut watcher = watcher(&dir, state_dir.join("state.json"));
+    let calls = Cell::new(0);
+
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(poll(&mut watcher, &calls), 1);
+    assert!(!state_dir.exists());
+
+    // the state is saved on the next poll once possible
+    fs::create_dir(&state_dir).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    let state = WatchState::load(&state_dir.join("state.json")).unwrap();
+    assert_eq!(state.indexed.len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keeps_running_when_a_directory_disappears() {
+    let dir = watch_dir("watch_removed_dir");
+    let watched = dir.join("watched");
+    fs::create_dir(&watched).unwrap();
+    let mut watcher = watcher(&watched, dir.join("state.json"));
+    let calls = Cell::new(0);
+
+    fs::remove_dir(&watched).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+
+    fs::create_dir(&watched).unwrap();
+    fs::write(watched.join("a.tdms"), hvds_bytes(2)).unwrap();
+    assert_eq!(poll(&mut watcher, &calls), 0);
+    assert_eq!(poll(&mut watcher, &calls), 1);
+    fs::remove_dir_all(dir).unwrap();
+}